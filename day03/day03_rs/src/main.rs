@@ -1,4 +1,4 @@
-use std::fs;
+use std::{fmt, fs};
 
 fn main() {
     let input = fs::read_to_string("input.txt")
@@ -10,6 +10,8 @@ fn main() {
     println!("Part 1: {}", part1);
     let part2 = solve2(&input, 12);
     println!("Part 2: {}", part2);
+    println!();
+    print!("{}", Report::new(&input, 12));
 }
 pub fn get_inverse(n: u32, bits: usize) -> u32 {
     n ^ (2_u32.pow(bits.try_into().unwrap()) - 1)
@@ -23,76 +25,206 @@ pub fn get_bit_at(n: u32, pos: usize) -> bool {
     }
 }
 
-pub fn common_bits(input: &[u32], bits: usize, most: bool) -> Vec<bool> {
-    let halfway = input.len() / 2;
-    let mut ones = vec![0; bits];
-    for val in input {
-        for pos in 1..=bits {
-            if get_bit_at(*val, pos) {
-                ones[pos - 1] += 1;
-            }
+/// Rules `reduce` follows while narrowing the input down to a single rating.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Policy {
+    /// Keep the most common bit (`true`) or the least common bit (`false`).
+    pub most_common: bool,
+    /// Bit to keep when ones and zeros are equally common.
+    pub tie: bool,
+    /// What to do when a bit position filters out every candidate.
+    pub on_empty: OnEmpty,
+    /// Stop as soon as a single candidate remains instead of checking every bit.
+    pub stop_at_unique: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnEmpty {
+    /// Give up, `reduce` returns `None`.
+    Fail,
+    /// Ignore the bit position and keep the previous candidates.
+    KeepPrevious,
+}
+
+impl Policy {
+    /// Oxygen generator rating: most common bit, ties keep 1.
+    pub fn oxygen() -> Self {
+        Self {
+            most_common: true,
+            tie: true,
+            on_empty: OnEmpty::Fail,
+            stop_at_unique: true,
         }
     }
-    let mut result = Vec::with_capacity(bits);
-    for n in ones.iter().take(bits) {
-        if *n > halfway {
-            if most {
-                result.push(true);
-            } else {
-                result.push(false);
-            }
-        } else if *n == halfway && input.len() % 2 == 0 {
-            if most {
-                result.push(true);
-            } else {
-                result.push(false)
-            }
-        } else if most {
-            result.push(false);
-        } else {
-            result.push(true);
+    /// CO2 scrubber rating: least common bit, ties keep 0.
+    pub fn co2() -> Self {
+        Self {
+            most_common: false,
+            tie: false,
+            on_empty: OnEmpty::Fail,
+            stop_at_unique: true,
         }
     }
-    result
 }
-pub fn solve1(input: &[u32], bits: usize) -> u32 {
-    let most_common = common_bits(input, bits, true);
-    let gamma_string = most_common
-        .iter()
-        .map(|b| if *b { '1' } else { '0' })
+
+pub fn common_bit_at(input: &[u32], pos: usize, most: bool, tie: bool) -> bool {
+    let ones = input.iter().filter(|&&n| get_bit_at(n, pos)).count();
+    let zeros = input.len() - ones;
+    match ones.cmp(&zeros) {
+        std::cmp::Ordering::Less => !most,
+        std::cmp::Ordering::Equal => tie,
+        std::cmp::Ordering::Greater => most,
+    }
+}
+
+pub fn common_bits_with(input: &[u32], bits: usize, most: bool, tie: bool) -> Vec<bool> {
+    (1..=bits)
+        .map(|pos| common_bit_at(input, pos, most, tie))
+        .collect()
+}
+
+pub fn common_bits(input: &[u32], bits: usize, most: bool) -> Vec<bool> {
+    // equal counts favour 1 for the most common and 0 for the least common bit
+    common_bits_with(input, bits, most, most)
+}
+/// Turns bits ordered from position 1 upwards back into a number.
+pub fn from_bits(bits: &[bool]) -> u32 {
+    bits.iter()
         .rev()
-        .collect::<String>();
-    let gamma_rate = u32::from_str_radix(&gamma_string, 2).unwrap();
+        .fold(0, |acc, &b| (acc << 1) | u32::from(b))
+}
+pub fn gamma_rate(input: &[u32], bits: usize) -> u32 {
+    from_bits(&common_bits(input, bits, true))
+}
+pub fn solve1(input: &[u32], bits: usize) -> u32 {
+    let gamma_rate = gamma_rate(input, bits);
     let epsilon_rate = get_inverse(gamma_rate, bits);
 
     gamma_rate * epsilon_rate
 }
-pub fn reduce(input: &[u32], bits: usize, most_common: bool) -> u32 {
-    let mut result = input.to_vec();
-    let mut cb = common_bits(&result, bits, most_common);
-    let mut temp = Vec::new();
-    for pos in (1..=bits).rev() {
-        for n in result.iter() {
-            if get_bit_at(*n, pos) == cb[pos - 1] {
-                temp.push(*n);
-                continue;
-            }
+
+/// Count of ones and zeros at a single bit position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitStats {
+    pub pos: usize,
+    pub ones: usize,
+    pub zeros: usize,
+}
+
+impl BitStats {
+    pub fn new(input: &[u32], pos: usize) -> Self {
+        let ones = input.iter().filter(|&&n| get_bit_at(n, pos)).count();
+        Self {
+            pos,
+            ones,
+            zeros: input.len() - ones,
         }
+    }
+    /// The most common bit, ties count as 1 like in `common_bits`.
+    pub fn majority(&self) -> bool {
+        self.ones >= self.zeros
+    }
+    pub fn margin(&self) -> usize {
+        self.ones.abs_diff(self.zeros)
+    }
+    pub fn is_tie(&self) -> bool {
+        self.ones == self.zeros
+    }
+}
+
+/// Per-bit histogram of the diagnostic report with the ratings derived from it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    pub bits: usize,
+    /// One entry per bit position, starting with the most significant bit.
+    pub stats: Vec<BitStats>,
+    pub gamma: u32,
+    pub epsilon: u32,
+    pub oxygen: Option<u32>,
+    pub co2: Option<u32>,
+}
 
-        if temp.len() == 1 {
-            return temp[0];
+impl Report {
+    pub fn new(input: &[u32], bits: usize) -> Self {
+        let gamma = gamma_rate(input, bits);
+        Self {
+            bits,
+            stats: (1..=bits)
+                .rev()
+                .map(|pos| BitStats::new(input, pos))
+                .collect(),
+            gamma,
+            epsilon: get_inverse(gamma, bits),
+            oxygen: reduce(input, bits, Policy::oxygen()),
+            co2: reduce(input, bits, Policy::co2()),
+        }
+    }
+    fn fmt_rating(&self, f: &mut fmt::Formatter, name: &str, rating: Option<u32>) -> fmt::Result {
+        match rating {
+            Some(n) => writeln!(f, "{:<8}{:0width$b} {}", name, n, n, width = self.bits),
+            None => writeln!(f, "{:<8}none", name),
         }
-        result.clear();
-        result.append(&mut temp);
-        cb = common_bits(&result, bits, most_common);
     }
-    0
 }
-pub fn solve2(input: &[u32], bits: usize) -> u32 {
-    let oxygen_rating = reduce(input, bits, true);
-    let co2_rating = reduce(input, bits, false);
 
-    oxygen_rating * co2_rating
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{:>4} {:>6} {:>6} {:>4} {:>6} tie",
+            "pos", "ones", "zeros", "bit", "margin"
+        )?;
+        for s in self.stats.iter() {
+            writeln!(
+                f,
+                "{:>4} {:>6} {:>6} {:>4} {:>6} {}",
+                s.pos,
+                s.ones,
+                s.zeros,
+                u8::from(s.majority()),
+                s.margin(),
+                if s.is_tie() { "yes" } else { "no" }
+            )?;
+        }
+        self.fmt_rating(f, "gamma", Some(self.gamma))?;
+        self.fmt_rating(f, "epsilon", Some(self.epsilon))?;
+        self.fmt_rating(f, "oxygen", self.oxygen)?;
+        self.fmt_rating(f, "co2", self.co2)
+    }
+}
+pub fn reduce(input: &[u32], bits: usize, policy: Policy) -> Option<u32> {
+    let mut candidates = input.to_vec();
+    for pos in (1..=bits).rev() {
+        if policy.stop_at_unique && candidates.len() == 1 {
+            break;
+        }
+        let keep = common_bit_at(&candidates, pos, policy.most_common, policy.tie);
+        let filtered = candidates
+            .iter()
+            .copied()
+            .filter(|&n| get_bit_at(n, pos) == keep)
+            .collect::<Vec<_>>();
+        if filtered.is_empty() {
+            match policy.on_empty {
+                OnEmpty::Fail => return None,
+                OnEmpty::KeepPrevious => continue,
+            }
+        }
+        candidates = filtered;
+    }
+    match candidates.split_first() {
+        Some((first, rest)) if rest.iter().all(|n| n == first) => Some(*first),
+        _ => None,
+    }
+}
+pub fn solve2_with(input: &[u32], bits: usize, oxygen: Policy, co2: Policy) -> Option<u32> {
+    let oxygen_rating = reduce(input, bits, oxygen)?;
+    let co2_rating = reduce(input, bits, co2)?;
+
+    Some(oxygen_rating * co2_rating)
+}
+pub fn solve2(input: &[u32], bits: usize) -> u32 {
+    solve2_with(input, bits, Policy::oxygen(), Policy::co2()).expect("no unique rating found")
 }
 
 #[cfg(test)]
@@ -102,7 +234,6 @@ mod tests {
     fn test_most_common() {
         let sequence = [30, 22, 23, 21, 28, 16, 25];
         let mcb = common_bits(&sequence, 5, true);
-        println!("most common: {:?}", &mcb);
         assert!(!mcb[0]);
         assert!(!mcb[1]);
         assert!(mcb[2]);
@@ -135,6 +266,7 @@ mod tests {
     }
     #[test]
     fn test_solve1() {
+        #[allow(clippy::useless_vec)]
         let input = vec![
             "00100", "11110", "10110", "10111", "10101", "01111", "00111", "11100", "10000",
             "11001", "00010", "01010",
@@ -148,6 +280,7 @@ mod tests {
 
     #[test]
     fn test_most_common_bits() {
+        #[allow(clippy::useless_vec)]
         let input = vec![
             "00100", "11110", "10110", "10111", "10101", "01111", "00111", "11100", "10000",
             "11001", "00010", "01010",
@@ -155,17 +288,53 @@ mod tests {
         .iter()
         .map(|l| u32::from_str_radix(l, 2).unwrap())
         .collect::<Vec<_>>();
-        println!("input: {:?}", &input);
         let most_common = common_bits(&input, 5, true);
-        println!("most common: {:?}", &most_common);
         assert!(!most_common[0]);
         assert!(most_common[1]);
         assert!(most_common[2]);
         assert!(!most_common[3]);
         assert!(most_common[4]);
     }
+    #[test]
+    fn test_report() {
+        let input = [
+            "00100", "11110", "10110", "10111", "10101", "01111", "00111", "11100", "10000",
+            "11001", "00010", "01010",
+        ]
+        .iter()
+        .map(|l| u32::from_str_radix(l, 2).unwrap())
+        .collect::<Vec<_>>();
+        let report = Report::new(&input, 5);
+        assert_eq!(
+            report.stats[0],
+            BitStats {
+                pos: 5,
+                ones: 7,
+                zeros: 5
+            }
+        );
+        assert_eq!(report.stats[0].margin(), 2);
+        assert!(report.stats.iter().all(|s| !s.is_tie()));
+        assert_eq!(
+            report
+                .stats
+                .iter()
+                .map(|s| s.majority())
+                .collect::<Vec<_>>(),
+            vec![true, false, true, true, false]
+        );
+        assert_eq!(report.gamma, 22);
+        assert_eq!(report.epsilon, 9);
+        assert_eq!(report.oxygen, Some(23));
+        assert_eq!(report.co2, Some(10));
+        let text = report.to_string();
+        assert!(text.contains("gamma   10110 22"));
+        assert!(text.contains("co2     01010 10"));
+    }
+
     #[test]
     fn test_reduce() {
+        #[allow(clippy::useless_vec)]
         let input = vec![
             "00100", "11110", "10110", "10111", "10101", "01111", "00111", "11100", "10000",
             "11001", "00010", "01010",
@@ -173,12 +342,51 @@ mod tests {
         .iter()
         .map(|l| u32::from_str_radix(l, 2).unwrap())
         .collect::<Vec<_>>();
-        assert_eq!(reduce(&input, 5, true), 23);
-        assert_eq!(reduce(&input, 5, false), 10);
+        assert_eq!(reduce(&input, 5, Policy::oxygen()), Some(23));
+        assert_eq!(reduce(&input, 5, Policy::co2()), Some(10));
+    }
+
+    #[test]
+    fn test_common_bits_tie() {
+        let input = [0b10, 0b01];
+        assert_eq!(common_bits(&input, 2, true), vec![true, true]);
+        assert_eq!(common_bits(&input, 2, false), vec![false, false]);
+        assert_eq!(common_bits_with(&input, 2, true, false), vec![false, false]);
+        assert_eq!(common_bits_with(&input, 2, false, true), vec![true, true]);
+    }
+
+    #[test]
+    fn test_reduce_policies() {
+        // every value shares the top bit, so the least common bit removes them all
+        let input = [0b110, 0b101, 0b100];
+        assert_eq!(reduce(&input, 3, Policy::co2()), None);
+        let keep_previous = Policy {
+            on_empty: OnEmpty::KeepPrevious,
+            ..Policy::co2()
+        };
+        assert_eq!(reduce(&input, 3, keep_previous), Some(0b110));
+
+        let ties_keep_zero = Policy {
+            tie: false,
+            ..Policy::oxygen()
+        };
+        assert_eq!(reduce(&[0b10, 0b01], 2, ties_keep_zero), Some(0b01));
+
+        let duplicates = [0b11, 0b11, 0b00];
+        assert_eq!(reduce(&duplicates, 2, Policy::oxygen()), Some(0b11));
+        assert_eq!(reduce(&duplicates, 2, Policy::co2()), Some(0b00));
+        // a lone candidate is still filtered, and its own bit is never the least common
+        let no_early_stop = Policy {
+            stop_at_unique: false,
+            ..Policy::co2()
+        };
+        assert_eq!(reduce(&duplicates, 2, no_early_stop), None);
+        assert_eq!(reduce(&[], 2, Policy::oxygen()), None);
     }
 
     #[test]
     fn test_solve2() {
+        #[allow(clippy::useless_vec)]
         let input = vec![
             "00100", "11110", "10110", "10111", "10101", "01111", "00111", "11100", "10000",
             "11001", "00010", "01010",
@@ -0,0 +1,224 @@
+use crate::{rules::WinRule, BingoBoard};
+use std::collections::BTreeSet;
+
+type Numbers = BTreeSet<usize>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Goal {
+    /// The target wins before every other board.
+    First,
+    /// Every other board wins before the target does.
+    Last,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// A draw order reaching the goal, `shortest` is false if the search gave up before it
+    /// could rule out shorter orders.
+    Found { order: Vec<usize>, shortest: bool },
+    /// No draw order reaches the goal.
+    Impossible,
+    /// The search budget ran out before any order was found.
+    Unknown,
+}
+
+/// The sets of numbers that make a board win when all of them are drawn.
+fn win_sets(board: &BingoBoard, rules: &[WinRule]) -> Vec<Numbers> {
+    let mut sets = Vec::<Numbers>::new();
+    for rule in rules {
+        for fields in rule.field_sets(board.height(), board.width()) {
+            let set = fields.iter().map(|&(r, c)| board.value(r, c)).collect();
+            if !sets.contains(&set) {
+                sets.push(set);
+            }
+        }
+    }
+    sets
+}
+
+/// Builds a draw order under which board `target` wins first or last. Finding the shortest
+/// order for `Goal::Last` is a search over one winning line per board, `budget` caps the
+/// number of visited search nodes.
+pub fn construct(
+    boards: &[BingoBoard],
+    target: usize,
+    goal: Goal,
+    rules: &[WinRule],
+    budget: usize,
+) -> Outcome {
+    let target_sets = win_sets(&boards[target], rules);
+    let mut others = boards
+        .iter()
+        .enumerate()
+        .filter(|&(b, _)| b != target)
+        .map(|(_, board)| win_sets(board, rules))
+        .collect::<Vec<_>>();
+    match goal {
+        // the line the target wins with is drawn completely, so any other board with a line
+        // inside of it has won by then as well
+        Goal::First => target_sets
+            .iter()
+            .filter(|line| !others.iter().flatten().any(|o| o.is_subset(line)))
+            .min_by_key(|line| line.len())
+            .map_or(Outcome::Impossible, |line| Outcome::Found {
+                order: line.iter().copied().collect(),
+                shortest: true,
+            }),
+        Goal::Last => {
+            // boards with the fewest usable lines narrow the search the most
+            others.sort_by_key(|sets| sets.len());
+            let mut search = LastSearch {
+                target_sets: &target_sets,
+                others: &others,
+                best: None,
+                nodes: 0,
+                budget,
+            };
+            search.visit(0, &Numbers::new());
+            let complete = search.nodes <= budget;
+            match search.best {
+                Some((drawn, finish)) => Outcome::Found {
+                    order: drawn.into_iter().chain(finish).collect(),
+                    shortest: complete,
+                },
+                None if complete => Outcome::Impossible,
+                None => Outcome::Unknown,
+            }
+        }
+    }
+}
+
+/// Depth first search picking a line for every other board, such that together they never
+/// complete a line of the target.
+struct LastSearch<'a> {
+    target_sets: &'a [Numbers],
+    others: &'a [Vec<Numbers>],
+    /// Numbers letting every other board win, followed by the rest of a target line.
+    best: Option<(Numbers, Vec<usize>)>,
+    nodes: usize,
+    budget: usize,
+}
+
+impl<'a> LastSearch<'a> {
+    fn best_len(&self) -> usize {
+        self.best
+            .as_ref()
+            .map_or(usize::MAX, |(drawn, finish)| drawn.len() + finish.len())
+    }
+    fn visit(&mut self, board: usize, drawn: &Numbers) {
+        self.nodes += 1;
+        // the target needs at least one more number after the other boards
+        if self.nodes > self.budget || drawn.len() + 1 >= self.best_len() {
+            return;
+        }
+        let sets = match self.others.get(board) {
+            Some(sets) => sets,
+            None => {
+                let finish = self
+                    .target_sets
+                    .iter()
+                    .map(|line| line.difference(drawn).copied().collect::<Vec<_>>())
+                    .min_by_key(|rest| rest.len());
+                if let Some(finish) = finish {
+                    if drawn.len() + finish.len() < self.best_len() {
+                        self.best = Some((drawn.clone(), finish));
+                    }
+                }
+                return;
+            }
+        };
+        if sets.iter().any(|line| line.is_subset(drawn)) {
+            self.visit(board + 1, drawn);
+            return;
+        }
+        let mut candidates = sets
+            .iter()
+            .map(|line| drawn.union(line).copied().collect::<Numbers>())
+            .filter(|next| !self.target_sets.iter().any(|t| t.is_subset(next)))
+            .collect::<Vec<_>>();
+        candidates.sort_by_key(|next| next.len());
+        for next in candidates {
+            self.visit(board + 1, &next);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        fixtures::{board, EXAMPLE_DATA},
+        parse_puzzle_data, play,
+        rules::STANDARD,
+    };
+
+    fn check(input: &str, target: usize, goal: Goal) -> Vec<usize> {
+        let (_, boards) = parse_puzzle_data(input);
+        let order = match construct(&boards, target, goal, STANDARD, 100_000) {
+            Outcome::Found { order, .. } => order,
+            outcome => panic!("no order for board {}: {:?}", target, outcome),
+        };
+        let (_, mut boards) = parse_puzzle_data(input);
+        let timeline = play(&order, &mut boards, STANDARD);
+        let win = match goal {
+            Goal::First => timeline.first(),
+            Goal::Last => timeline.last(),
+        };
+        assert_eq!(win.map(|w| w.board), Some(target));
+        assert_eq!(win.map(|w| w.turn), Some(order.len()));
+        // nobody else wins on the same turn
+        assert_eq!(
+            timeline
+                .wins
+                .iter()
+                .filter(|w| w.turn == order.len())
+                .count(),
+            1
+        );
+        order
+    }
+
+    #[test]
+    fn test_construct_first() {
+        for target in 0..3 {
+            assert_eq!(check(EXAMPLE_DATA, target, Goal::First).len(), 5);
+        }
+    }
+
+    #[test]
+    fn test_construct_last() {
+        for target in 0..3 {
+            // the other boards need a line each before the target gets its last number
+            assert!(check(EXAMPLE_DATA, target, Goal::Last).len() > 5);
+        }
+    }
+
+    #[test]
+    fn test_construct_impossible() {
+        // the first and the third board are the same, so neither can win alone
+        let boards = [
+            board(&[&[1, 2], &[3, 4]]),
+            board(&[&[1, 2, 9], &[5, 6, 7]]),
+            board(&[&[1, 2], &[3, 4]]),
+        ];
+        assert_eq!(
+            construct(&boards, 1, Goal::First, STANDARD, 1000),
+            Outcome::Found {
+                order: vec![1, 5],
+                shortest: true
+            }
+        );
+        assert_eq!(
+            construct(&boards, 0, Goal::First, STANDARD, 1000),
+            Outcome::Impossible
+        );
+        assert_eq!(
+            construct(&boards, 0, Goal::Last, STANDARD, 1000),
+            Outcome::Impossible
+        );
+        assert_eq!(
+            construct(&boards, 0, Goal::Last, STANDARD, 0),
+            Outcome::Unknown
+        );
+    }
+}
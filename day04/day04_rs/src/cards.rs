@@ -0,0 +1,315 @@
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+/// Something wrong with a puzzle input, boards, rows and columns count from 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    /// A drawn number or a field that isn't a number.
+    NotANumber {
+        line: usize,
+        text: String,
+    },
+    NoDrawnNumbers,
+    DrawnTwice {
+        number: usize,
+    },
+    /// A drawn number that doesn't appear on any board.
+    NeverOnBoard {
+        number: usize,
+    },
+    UnevenRow {
+        board: usize,
+        row: usize,
+        len: usize,
+        expected: usize,
+    },
+    DuplicateNumber {
+        board: usize,
+        number: usize,
+        first: (usize, usize),
+        second: (usize, usize),
+    },
+    NoBoards,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Problem::NotANumber { line, text } => {
+                write!(f, "Line {}: {:?} is not a number.", line, text)
+            }
+            Problem::NoDrawnNumbers => write!(f, "No numbers are drawn."),
+            Problem::DrawnTwice { number } => write!(f, "{} is drawn more than once.", number),
+            Problem::NeverOnBoard { number } => {
+                write!(f, "{} is drawn but not on any board.", number)
+            }
+            Problem::UnevenRow {
+                board,
+                row,
+                len,
+                expected,
+            } => write!(
+                f,
+                "Board {} row {} has {} numbers, expected {}.",
+                board, row, len, expected
+            ),
+            Problem::DuplicateNumber {
+                board,
+                number,
+                first,
+                second,
+            } => write!(
+                f,
+                "Board {} has {} at row {} column {} and row {} column {}.",
+                board, number, first.0, first.1, second.0, second.1
+            ),
+            Problem::NoBoards => write!(f, "There are no boards."),
+        }
+    }
+}
+
+fn parse_numbers<'a>(
+    line: usize,
+    parts: impl Iterator<Item = &'a str>,
+    problems: &mut Vec<Problem>,
+) -> Vec<usize> {
+    parts
+        .filter_map(|text| match text.trim().parse() {
+            Ok(n) => Some(n),
+            Err(_) => {
+                problems.push(Problem::NotANumber {
+                    line,
+                    text: text.to_string(),
+                });
+                None
+            }
+        })
+        .collect()
+}
+
+/// Checks an input in the puzzle format and reports every problem found, unlike
+/// `parse_puzzle_data` which panics on the first one.
+pub fn validate(input: &str) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    let mut lines = input.lines().enumerate();
+    let drawn = match lines.next() {
+        Some((_, line)) if !line.trim().is_empty() => {
+            parse_numbers(1, line.split(','), &mut problems)
+        }
+        _ => {
+            problems.push(Problem::NoDrawnNumbers);
+            Vec::new()
+        }
+    };
+
+    // every board as its rows of numbers
+    let mut boards: Vec<Vec<Vec<usize>>> = Vec::new();
+    let mut rows = Vec::new();
+    for (i, line) in lines.chain(std::iter::once((0, ""))) {
+        if line.trim().is_empty() {
+            if !rows.is_empty() {
+                boards.push(std::mem::take(&mut rows));
+            }
+            continue;
+        }
+        rows.push(parse_numbers(i + 1, line.split_whitespace(), &mut problems));
+    }
+    if boards.is_empty() {
+        problems.push(Problem::NoBoards);
+    }
+
+    let mut on_board = HashSet::new();
+    for (b, rows) in boards.iter().enumerate() {
+        let width = rows[0].len();
+        for (r, row) in rows.iter().enumerate().skip(1) {
+            if row.len() != width {
+                problems.push(Problem::UnevenRow {
+                    board: b + 1,
+                    row: r + 1,
+                    len: row.len(),
+                    expected: width,
+                });
+            }
+        }
+        let mut seen = HashMap::new();
+        for (r, row) in rows.iter().enumerate() {
+            for (c, &number) in row.iter().enumerate() {
+                on_board.insert(number);
+                if let Some(&first) = seen.get(&number) {
+                    problems.push(Problem::DuplicateNumber {
+                        board: b + 1,
+                        number,
+                        first,
+                        second: (r + 1, c + 1),
+                    });
+                } else {
+                    seen.insert(number, (r + 1, c + 1));
+                }
+            }
+        }
+    }
+
+    let mut drawn_before = HashSet::new();
+    for &number in drawn.iter() {
+        if !drawn_before.insert(number) {
+            problems.push(Problem::DrawnTwice { number });
+        } else if !on_board.contains(&number) {
+            problems.push(Problem::NeverOnBoard { number });
+        }
+    }
+    problems
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CardOptions {
+    pub boards: usize,
+    pub height: usize,
+    pub width: usize,
+    /// Numbers on the cards are taken from `0..=max_number`.
+    pub max_number: usize,
+    pub seed: u64,
+}
+
+impl Default for CardOptions {
+    fn default() -> Self {
+        Self {
+            boards: 100,
+            height: 5,
+            width: 5,
+            max_number: 99,
+            seed: 0,
+        }
+    }
+}
+
+/// Generates random cards and a draw order in the puzzle format. Every card holds distinct
+/// numbers and every number on a card is drawn exactly once, so the result always passes
+/// `validate`. Panics when asked for no cards at all.
+pub fn generate(options: CardOptions) -> String {
+    let size = options.height * options.width;
+    assert!(options.boards > 0, "There have to be cards to generate.");
+    assert!(
+        size > 0 && size <= options.max_number + 1,
+        "{}x{} cards need at least {} different numbers.",
+        options.height,
+        options.width,
+        size
+    );
+    let mut rng = StdRng::seed_from_u64(options.seed);
+    let mut numbers = (0..=options.max_number).collect::<Vec<_>>();
+    let mut used = vec![false; options.max_number + 1];
+    let digits = options.max_number.to_string().len();
+
+    let mut cards = String::new();
+    for _ in 0..options.boards {
+        numbers.shuffle(&mut rng);
+        cards.push('\n');
+        for row in numbers[..size].chunks(options.width) {
+            let row = row
+                .iter()
+                .map(|&n| {
+                    used[n] = true;
+                    format!("{:>width$}", n, width = digits)
+                })
+                .collect::<Vec<_>>();
+            cards.push_str(&row.join(" "));
+            cards.push('\n');
+        }
+    }
+
+    let mut drawn = (0..=options.max_number)
+        .filter(|&n| used[n])
+        .collect::<Vec<_>>();
+    drawn.shuffle(&mut rng);
+    let drawn = drawn.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+    format!("{}\n{}", drawn.join(","), cards)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_puzzle_data, play, rules::STANDARD};
+
+    #[test]
+    fn test_validate() {
+        let input = "1,2,2,40,x
+
+1 2 3
+4 5
+6 6 7
+
+1 2
+3 4";
+        assert_eq!(
+            validate(input),
+            vec![
+                Problem::NotANumber {
+                    line: 1,
+                    text: "x".to_string()
+                },
+                Problem::UnevenRow {
+                    board: 1,
+                    row: 2,
+                    len: 2,
+                    expected: 3
+                },
+                Problem::DuplicateNumber {
+                    board: 1,
+                    number: 6,
+                    first: (3, 1),
+                    second: (3, 2)
+                },
+                Problem::DrawnTwice { number: 2 },
+                Problem::NeverOnBoard { number: 40 },
+            ]
+        );
+        assert_eq!(
+            validate(input)[2].to_string(),
+            "Board 1 has 6 at row 3 column 1 and row 3 column 2."
+        );
+        assert_eq!(
+            validate(""),
+            vec![Problem::NoDrawnNumbers, Problem::NoBoards]
+        );
+    }
+
+    #[test]
+    fn test_generate() {
+        let options = CardOptions {
+            boards: 20,
+            height: 7,
+            width: 7,
+            max_number: 75,
+            seed: 3,
+        };
+        let input = generate(options);
+        assert_eq!(validate(&input), vec![]);
+        assert_eq!(input, generate(options));
+        assert_ne!(input, generate(CardOptions { seed: 4, ..options }));
+
+        let (drawn_numbers, mut boards) = parse_puzzle_data(&input);
+        assert_eq!(boards.len(), 20);
+        assert!(boards.iter().all(|b| b.height() == 7 && b.width() == 7));
+        // every number is drawn, so every board wins eventually
+        let timeline = play(&drawn_numbers, &mut boards, STANDARD);
+        assert!(timeline.never_won.is_empty());
+    }
+
+    #[test]
+    fn test_validate_mixed_sizes() {
+        let input = "1,2,3,4\n\n1 2 3\n4 5 6\n\n7 8\n9 10\n";
+        assert_eq!(validate(input), vec![]);
+    }
+
+    #[test]
+    #[should_panic(expected = "There have to be cards to generate.")]
+    fn test_generate_no_cards() {
+        generate(CardOptions {
+            boards: 0,
+            ..CardOptions::default()
+        });
+    }
+}
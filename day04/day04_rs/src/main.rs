@@ -1,8 +1,22 @@
+use adversary::{construct, Goal, Outcome};
+use cards::{generate, validate, CardOptions};
 use lazy_static::lazy_static;
 use regex::Regex;
-use std::{collections::HashMap, fs};
+use rules::{Pattern, WinLine, WinRule, STANDARD};
+use simulation::{simulate, SimulationOptions};
+use std::{
+    collections::{HashMap, VecDeque},
+    fs,
+    time::Duration,
+};
 
-#[derive(Debug, PartialEq, Eq)]
+mod adversary;
+mod cards;
+mod rules;
+mod simulation;
+mod tui;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct BingoField {
     val: usize,
     marked: bool,
@@ -15,54 +29,166 @@ impl BingoField {
         self.marked = true;
     }
 }
+#[derive(Debug, Clone)]
 pub struct BingoBoard {
     rows: Vec<Vec<BingoField>>,
     won: bool,
+    line: Option<WinLine>,
+    // marked fields per row, column, diagonal, corner and pattern, so wins can be checked
+    // without a rescan
+    row_marks: Vec<usize>,
+    column_marks: Vec<usize>,
+    diagonal_marks: [usize; 2],
+    corner_marks: usize,
+    pattern_marks: Vec<PatternMarks>,
+    marked: usize,
+    unmarked_sum: usize,
+}
+/// The marked fields of a pattern, counted up to some number of marks on the board.
+#[derive(Debug, Clone)]
+struct PatternMarks {
+    pattern: usize,
+    count: usize,
+    marked: usize,
 }
 impl BingoBoard {
     pub fn new(rows: Vec<Vec<BingoField>>) -> Self {
-        Self { rows, won: false }
-    }
-    pub fn mark(&mut self, val: usize) {
-        self.rows.iter_mut().for_each(|row| {
-            row.iter_mut().filter(|f| f.val == val).for_each(|field| {
-                field.mark();
-            });
-        });
-        self.check_win();
-    }
-    pub fn check_win(&mut self) {
-        // check if we have a full row with marked fields
-        for row in self.rows.iter() {
-            if row.iter().filter(|f| f.marked).count() == 5 {
-                self.won = true;
-                return;
+        let height = rows.len();
+        let width = rows.first().map_or(0, |row| row.len());
+        let mut board = Self {
+            rows,
+            won: false,
+            line: None,
+            row_marks: vec![0; height],
+            column_marks: vec![0; width],
+            diagonal_marks: [0; 2],
+            corner_marks: 0,
+            pattern_marks: Vec::new(),
+            marked: 0,
+            unmarked_sum: 0,
+        };
+        for r in 0..height {
+            for c in 0..width {
+                let val = board.rows[r][c].val;
+                if board.rows[r][c].marked {
+                    board.count_mark(r, c);
+                } else {
+                    board.unmarked_sum += val;
+                }
             }
         }
-        // check if we have a full column with marked fields
-        let mut marked = 0;
-        for column in 0..4 {
-            for row in self.rows.iter() {
-                if row[column].marked {
-                    marked += 1;
+        board
+    }
+    /// Marks `val` wherever it is on the board. A `Game` finds the fields through its index
+    /// instead of looking at every one.
+    pub fn mark(&mut self, val: usize, rules: &[WinRule]) {
+        for r in 0..self.height() {
+            for c in 0..self.width() {
+                if self.rows[r][c].val == val {
+                    self.mark_at(r, c, rules);
                 }
             }
-            if marked == 5 {
-                self.won = true;
-                return;
-            }
-            marked = 0;
         }
     }
-    pub fn calculate_score(&self, called: usize) -> usize {
-        let mut score = 0;
-        for row in self.rows.iter() {
-            score += row
+    /// Marks a single field and only checks the lines running through it. Returns true if
+    /// this mark made the board win.
+    pub fn mark_at(&mut self, row: usize, column: usize, rules: &[WinRule]) -> bool {
+        let field = &mut self.rows[row][column];
+        if field.marked {
+            return false;
+        }
+        field.mark();
+        self.unmarked_sum -= field.val;
+        self.count_mark(row, column);
+        self.count_pattern_marks(row, column, rules);
+        if self.won {
+            return false;
+        }
+        self.line = rules
+            .iter()
+            .find_map(|rule| rule.line_at(self, row, column));
+        self.won = self.line.is_some();
+        self.won
+    }
+    fn count_mark(&mut self, row: usize, column: usize) {
+        self.row_marks[row] += 1;
+        self.column_marks[column] += 1;
+        if row == column {
+            self.diagonal_marks[0] += 1;
+        }
+        if row + column + 1 == self.width() {
+            self.diagonal_marks[1] += 1;
+        }
+        if (row == 0 || row + 1 == self.height()) && (column == 0 || column + 1 == self.width()) {
+            self.corner_marks += 1;
+        }
+        self.marked += 1;
+    }
+    /// Counts the mark for the patterns among the rules. A pattern counter that missed marks,
+    /// made with other rules, counts the marked fields again.
+    fn count_pattern_marks(&mut self, row: usize, column: usize, rules: &[WinRule]) {
+        for rule in rules {
+            let WinRule::Pattern(pattern) = rule else {
+                continue;
+            };
+            let marked = self.marked;
+            let found = self
+                .pattern_marks
                 .iter()
-                .filter(|f| !f.marked)
-                .fold(0, |acc, field| acc + field.val);
+                .position(|m| m.pattern == pattern.id());
+            if let Some(i) = found.filter(|&i| self.pattern_marks[i].marked + 1 == marked) {
+                let marks = &mut self.pattern_marks[i];
+                if pattern.requires(row, column) {
+                    marks.count += 1;
+                }
+                marks.marked = marked;
+                continue;
+            }
+            let count = (0..self.height())
+                .flat_map(|r| (0..self.width()).map(move |c| (r, c)))
+                .filter(|&(r, c)| pattern.requires(r, c) && self.is_marked(r, c))
+                .count();
+            let marks = PatternMarks {
+                pattern: pattern.id(),
+                count,
+                marked,
+            };
+            match found {
+                Some(i) => self.pattern_marks[i] = marks,
+                None => self.pattern_marks.push(marks),
+            }
         }
-        score * called
+    }
+    /// The marked fields of a pattern, as of the last mark made with it among the rules.
+    fn pattern_marks(&self, pattern: &Pattern) -> usize {
+        self.pattern_marks
+            .iter()
+            .find(|m| m.pattern == pattern.id())
+            .map_or(0, |m| m.count)
+    }
+    pub fn height(&self) -> usize {
+        self.rows.len()
+    }
+    pub fn width(&self) -> usize {
+        self.rows.first().map_or(0, |row| row.len())
+    }
+    /// The line the board won with, if it was marked one field at a time.
+    pub fn winning_line(&self) -> Option<WinLine> {
+        self.line
+    }
+    pub fn value(&self, row: usize, column: usize) -> usize {
+        self.rows[row][column].val
+    }
+    pub fn is_marked(&self, row: usize, column: usize) -> bool {
+        self.rows[row][column].marked
+    }
+    pub fn check_win(&mut self, rules: &[WinRule]) {
+        if rules.iter().any(|rule| rule.is_met(self)) {
+            self.won = true;
+        }
+    }
+    pub fn calculate_score(&self, called: usize) -> usize {
+        self.unmarked_sum * called
     }
     pub fn parse_row(s: &str) -> Vec<BingoField> {
         lazy_static! {
@@ -75,47 +201,257 @@ impl BingoBoard {
             .collect()
     }
 }
+/// Boards of one game together with an index from number to the fields holding it.
+pub struct Game<'a> {
+    boards: &'a mut [BingoBoard],
+    index: HashMap<usize, Vec<(usize, usize, usize)>>,
+}
+impl<'a> Game<'a> {
+    pub fn new(boards: &'a mut [BingoBoard]) -> Self {
+        let mut index: HashMap<usize, Vec<_>> = HashMap::new();
+        for (b, board) in boards.iter().enumerate() {
+            for (r, row) in board.rows.iter().enumerate() {
+                for (c, field) in row.iter().enumerate() {
+                    index.entry(field.val).or_default().push((b, r, c));
+                }
+            }
+        }
+        Self { boards, index }
+    }
+    /// Marks `val` on every board holding it and returns the boards that won with it, in
+    /// board order.
+    pub fn draw(&mut self, val: usize, rules: &[WinRule]) -> Vec<usize> {
+        let mut winners = Vec::new();
+        if let Some(fields) = self.index.get(&val) {
+            for &(b, r, c) in fields {
+                if self.boards[b].mark_at(r, c, rules) {
+                    winners.push(b);
+                }
+            }
+        }
+        winners
+    }
+    pub fn board(&self, board: usize) -> &BingoBoard {
+        &self.boards[board]
+    }
+}
 fn main() {
     let input = fs::read_to_string("input.txt").unwrap();
+    let mut rules = STANDARD.to_vec();
+    let mut simulation = None;
+    let mut fixtures_budget = None;
+    let mut cards = None;
+    let mut seed = 0;
+    let mut interactive = false;
+    let mut interval = None;
+    let mut threads = 1;
+    let mut check_input = false;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().expect("missing argument value");
+        match arg.as_str() {
+            // extra winning patterns
+            "--patterns" => {
+                let patterns = fs::read_to_string(value()).unwrap();
+                rules.extend(
+                    rules::parse_patterns(&patterns)
+                        .into_iter()
+                        .map(WinRule::Pattern),
+                );
+            }
+            "--simulate" => simulation = Some(value().parse().unwrap()),
+            "--seed" => seed = value().parse().unwrap(),
+            "--threads" => threads = value().parse().unwrap(),
+            // draw orders making every board win first and last
+            "--fixtures" => fixtures_budget = Some(value().parse().unwrap()),
+            // prints a new puzzle input with the given number of boards
+            "--generate" => cards = Some(value().parse().unwrap()),
+            // report every problem with the input instead of solving it
+            "--validate" => check_input = true,
+            // step through the game in the terminal, optionally drawing every n milliseconds
+            "--tui" => interactive = true,
+            "--interval" => interval = Some(Duration::from_millis(value().parse().unwrap())),
+            _ => panic!("Unknown argument {}", arg),
+        }
+    }
+    if let Some(boards) = cards {
+        print!(
+            "{}",
+            generate(CardOptions {
+                boards,
+                seed,
+                ..CardOptions::default()
+            })
+        );
+        return;
+    }
+    if check_input {
+        let problems = validate(&input);
+        if problems.is_empty() {
+            println!("No problems found.");
+            return;
+        }
+        for problem in problems {
+            eprintln!("{}", problem);
+        }
+        std::process::exit(1);
+    }
     let (drawn_numbers, mut boards) = parse_puzzle_data(&input);
-    let part1 = solve1(&drawn_numbers, &mut boards);
+    if interactive {
+        let mut caller = tui::Caller::new(boards, &drawn_numbers, &rules);
+        tui::run(&mut caller, interval).unwrap();
+        return;
+    }
+    if let Some(trials) = simulation {
+        let options = SimulationOptions {
+            trials,
+            seed,
+            threads,
+        };
+        let odds = simulate(&boards, &drawn_numbers, &rules, options);
+        println!("board   first    last  turns");
+        for (b, o) in odds.iter().enumerate() {
+            let turns = o
+                .expected_turns
+                .map_or("-".to_string(), |t| format!("{:.1}", t));
+            println!("{:>5} {:>7.3} {:>7.3} {:>6}", b, o.first, o.last, turns);
+        }
+        return;
+    }
+    if let Some(budget) = fixtures_budget {
+        for b in 0..boards.len() {
+            for goal in [Goal::First, Goal::Last] {
+                let order = match construct(&boards, b, goal, &rules, budget) {
+                    Outcome::Found { order, shortest } => format!(
+                        "{}{}",
+                        order
+                            .iter()
+                            .map(|n| n.to_string())
+                            .collect::<Vec<_>>()
+                            .join(","),
+                        if shortest {
+                            ""
+                        } else {
+                            " (maybe not shortest)"
+                        }
+                    ),
+                    Outcome::Impossible => "impossible".to_string(),
+                    Outcome::Unknown => "unknown".to_string(),
+                };
+                println!("board {} {:?}: {}", b, goal, order);
+            }
+        }
+        return;
+    }
+    let part1 = solve1(&drawn_numbers, &mut boards, &rules);
     println!("Part 1: {}", &part1);
-    let part2 = solve2(&drawn_numbers, &mut boards);
+    // part 1 marked the boards up to the first win, start over with clean boards
+    let (_, mut boards) = parse_puzzle_data(&input);
+    let part2 = solve2(&drawn_numbers, &mut boards, &rules);
     println!("Part 2: {:?}", &part2);
 }
 
-fn solve2(drawn_numbers: &[usize], boards: &mut [BingoBoard]) -> (usize, usize) {
-    let mut remaining = boards.len();
-    let mut winning_boards = HashMap::with_capacity(boards.len());
+/// A board winning during a game.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WinEvent {
+    pub board: usize,
+    /// Number of drawn numbers so far, starting at 1.
+    pub turn: usize,
+    pub called: usize,
+    pub line: WinLine,
+    pub score: usize,
+}
 
-    for n in drawn_numbers {
-        for (board_number, board) in boards.iter_mut().enumerate() {
-            board.mark(*n);
-            if board.won {
-                winning_boards.entry(board_number).or_insert_with(|| {
-                    remaining -= 1;
-                    board.calculate_score(*n)
-                });
+/// Everything that happened during a game.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Timeline {
+    /// Wins in the order they happened, boards winning on the same turn are in board order.
+    pub wins: Vec<WinEvent>,
+    /// Boards that had not won when the numbers ran out.
+    pub never_won: Vec<usize>,
+}
+impl Timeline {
+    pub fn first(&self) -> Option<&WinEvent> {
+        self.wins.first()
+    }
+    pub fn last(&self) -> Option<&WinEvent> {
+        self.wins.last()
+    }
+}
 
-                if remaining == 0 {
-                    return (*n, board.calculate_score(*n));
-                }
-            }
+/// The wins of a game in the order they happen, drawing numbers only as far as needed to
+/// find the next one.
+pub struct Wins<'a> {
+    game: Game<'a>,
+    drawn_numbers: &'a [usize],
+    rules: &'a [WinRule],
+    /// Numbers drawn so far.
+    turn: usize,
+    remaining: usize,
+    /// Wins of the current turn that weren't returned yet.
+    pending: VecDeque<WinEvent>,
+}
+impl<'a> Wins<'a> {
+    pub fn new(
+        drawn_numbers: &'a [usize],
+        boards: &'a mut [BingoBoard],
+        rules: &'a [WinRule],
+    ) -> Self {
+        Self {
+            remaining: boards.iter().filter(|b| !b.won).count(),
+            game: Game::new(boards),
+            drawn_numbers,
+            rules,
+            turn: 0,
+            pending: VecDeque::new(),
         }
     }
-    (0, 0)
 }
-
-fn solve1(drawn_numbers: &[usize], boards: &mut [BingoBoard]) -> usize {
-    for n in drawn_numbers {
-        for board in boards.iter_mut() {
-            board.mark(*n);
-            if board.won {
-                return board.calculate_score(*n);
+impl Iterator for Wins<'_> {
+    type Item = WinEvent;
+    fn next(&mut self) -> Option<WinEvent> {
+        while self.pending.is_empty() {
+            if self.remaining == 0 || self.turn == self.drawn_numbers.len() {
+                return None;
+            }
+            let n = self.drawn_numbers[self.turn];
+            self.turn += 1;
+            for board_number in self.game.draw(n, self.rules) {
+                let board = self.game.board(board_number);
+                self.pending.push_back(WinEvent {
+                    board: board_number,
+                    turn: self.turn,
+                    called: n,
+                    line: board.winning_line().unwrap(),
+                    score: board.calculate_score(n),
+                });
+                self.remaining -= 1;
             }
         }
+        self.pending.pop_front()
     }
-    0
+}
+
+/// Draws all numbers and records every board that wins.
+pub fn play(drawn_numbers: &[usize], boards: &mut [BingoBoard], rules: &[WinRule]) -> Timeline {
+    let mut game = Wins::new(drawn_numbers, boards, rules);
+    let wins = game.by_ref().collect();
+    let never_won = (0..game.game.boards.len())
+        .filter(|&b| !game.game.board(b).won)
+        .collect();
+    Timeline { wins, never_won }
+}
+
+fn solve2(drawn_numbers: &[usize], boards: &mut [BingoBoard], rules: &[WinRule]) -> (usize, usize) {
+    play(drawn_numbers, boards, rules)
+        .last()
+        .map_or((0, 0), |win| (win.called, win.score))
+}
+
+fn solve1(drawn_numbers: &[usize], boards: &mut [BingoBoard], rules: &[WinRule]) -> usize {
+    Wins::new(drawn_numbers, boards, rules)
+        .next()
+        .map_or(0, |win| win.score)
 }
 
 pub fn parse_puzzle_data(input: &str) -> (Vec<usize>, Vec<BingoBoard>) {
@@ -126,26 +462,38 @@ pub fn parse_puzzle_data(input: &str) -> (Vec<usize>, Vec<BingoBoard>) {
         .split(',')
         .map(|c| c.parse::<usize>().unwrap())
         .collect::<Vec<_>>();
-    let mut raw_boards = lines.filter(|l| !l.trim().is_empty()).peekable();
     let mut boards = Vec::new();
-    while raw_boards.peek().is_some() {
-        let mut rows = Vec::with_capacity(5);
-        for _ in 0..5 {
-            if let Some(s) = raw_boards.next() {
-                rows.push(BingoBoard::parse_row(s));
-            } else {
-                panic!("Unable to finish board.")
+    let mut rows: Vec<Vec<BingoField>> = Vec::new();
+    // boards are separated by blank lines, the size of each board follows from its rows
+    for line in lines.chain(std::iter::once("")) {
+        if line.trim().is_empty() {
+            if !rows.is_empty() {
+                boards.push(BingoBoard::new(std::mem::take(&mut rows)));
+            }
+            continue;
+        }
+        let row = BingoBoard::parse_row(line);
+        if let Some(first) = rows.first() {
+            if row.len() != first.len() {
+                panic!(
+                    "Board {} row {} has {} numbers, expected {}.",
+                    boards.len() + 1,
+                    rows.len() + 1,
+                    row.len(),
+                    first.len()
+                );
             }
         }
-        boards.push(BingoBoard::new(rows));
+        rows.push(row);
     }
     (drawn_numbers, boards)
 }
 
 #[cfg(test)]
-mod tests {
-    use super::*;
-    static EXAMPLE_DATA: &str =
+mod fixtures {
+    use super::{BingoBoard, BingoField};
+
+    pub static EXAMPLE_DATA: &str =
         "7,4,9,5,11,17,23,2,0,14,21,24,10,16,13,6,15,25,12,22,18,20,8,19,3,26,1
 
 22 13 17 11  0
@@ -166,6 +514,20 @@ mod tests {
 22 11 13  6  5
  2  0 12  3  7";
 
+    pub fn board(rows: &[&[usize]]) -> BingoBoard {
+        BingoBoard::new(
+            rows.iter()
+                .map(|r| r.iter().map(|&n| BingoField::new(n)).collect())
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::EXAMPLE_DATA;
+
     #[test]
     fn test_parse_puzzle_data() {
         let (drawn_numbers, boards) = parse_puzzle_data(EXAMPLE_DATA);
@@ -224,7 +586,7 @@ mod tests {
         let mut result = 0;
         for n in drawn_numbers.iter() {
             for board in boards.iter_mut() {
-                board.mark(*n);
+                board.mark(*n, STANDARD);
                 if board.won {
                     result = board.calculate_score(*n);
                     break;
@@ -234,9 +596,113 @@ mod tests {
         assert_eq!(result, 4512);
     }
 
+    #[test]
+    fn test_check_win_last_column() {
+        let mut board = BingoBoard::new(
+            (0..7)
+                .map(|r| (0..7).map(|c| BingoField::new(r * 7 + c)).collect())
+                .collect(),
+        );
+        for r in 0..7 {
+            assert!(!board.won);
+            board.mark(r * 7 + 6, STANDARD);
+        }
+        assert!(board.won);
+    }
+
+    #[test]
+    fn test_parse_rectangular_boards() {
+        let input = "1,2,3,4
+
+1 2 3
+4 5 6
+
+7 8 9
+10 11 12
+13 14 15
+16 17 18";
+        let (drawn_numbers, mut boards) = parse_puzzle_data(input);
+        assert_eq!(boards.len(), 2);
+        assert_eq!((boards[0].height(), boards[0].width()), (2, 3));
+        assert_eq!((boards[1].height(), boards[1].width()), (4, 3));
+        assert_eq!(solve1(&drawn_numbers, &mut boards, STANDARD), 15 * 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "Board 1 row 2 has 2 numbers, expected 3.")]
+    fn test_parse_uneven_rows() {
+        parse_puzzle_data("1,2\n\n1 2 3\n4 5\n");
+    }
+
+    #[test]
+    fn test_game_draw() {
+        let (drawn_numbers, mut boards) = parse_puzzle_data(EXAMPLE_DATA);
+        let mut game = Game::new(&mut boards);
+        let mut first = None;
+        for n in drawn_numbers.iter() {
+            let winners = game.draw(*n, STANDARD);
+            if !winners.is_empty() {
+                first = Some((*n, winners));
+                break;
+            }
+        }
+        assert_eq!(first, Some((24, vec![2])));
+        assert_eq!(game.board(2).calculate_score(24), 4512);
+        // a number is only counted once per field
+        assert!(game.draw(24, STANDARD).is_empty());
+        assert_eq!(game.board(2).marked, 12);
+        assert_eq!(game.board(2).row_marks[0], 5);
+    }
+
+    #[test]
+    fn test_play() {
+        let (drawn_numbers, mut boards) = parse_puzzle_data(EXAMPLE_DATA);
+        let timeline = play(&drawn_numbers, &mut boards, STANDARD);
+        assert_eq!(
+            timeline.first(),
+            Some(&WinEvent {
+                board: 2,
+                turn: 12,
+                called: 24,
+                line: WinLine::Row(0),
+                score: 4512
+            })
+        );
+        assert_eq!(
+            timeline.wins.iter().map(|w| w.board).collect::<Vec<_>>(),
+            vec![2, 0, 1]
+        );
+        assert_eq!(timeline.last().map(|w| (w.turn, w.score)), Some((15, 1924)));
+        assert!(timeline.never_won.is_empty());
+
+        let (_, mut boards) = parse_puzzle_data(EXAMPLE_DATA);
+        let timeline = play(&drawn_numbers[..12], &mut boards, STANDARD);
+        assert_eq!(timeline.wins.len(), 1);
+        assert_eq!(timeline.never_won, vec![0, 1]);
+    }
+
+    #[test]
+    fn test_wins_stop_at_first() {
+        let (drawn_numbers, mut boards) = parse_puzzle_data(EXAMPLE_DATA);
+        let mut wins = Wins::new(&drawn_numbers, &mut boards, STANDARD);
+        assert_eq!(wins.next().map(|w| (w.board, w.turn)), Some((2, 12)));
+        // nothing after the winning number was drawn
+        assert_eq!(wins.turn, 12);
+        assert_eq!(wins.game.board(0).marked, 12);
+        assert_eq!(wins.map(|w| w.board).collect::<Vec<_>>(), vec![0, 1]);
+    }
+
     #[test]
     fn test_solve2() {
         let (drawn_numbers, mut boards) = parse_puzzle_data(EXAMPLE_DATA);
-        assert_eq!(solve2(&drawn_numbers, &mut boards), (13, 1924));
+        assert_eq!(solve2(&drawn_numbers, &mut boards, STANDARD), (13, 1924));
+    }
+
+    #[test]
+    fn test_solve_with_rules() {
+        let (drawn_numbers, mut boards) = parse_puzzle_data(EXAMPLE_DATA);
+        // 4, 9, 23, 11 and 2 are the anti-diagonal of the third board
+        let rules = [WinRule::Row, WinRule::Column, WinRule::Diagonal];
+        assert_eq!(solve1(&drawn_numbers, &mut boards, &rules), 247 * 2);
     }
 }
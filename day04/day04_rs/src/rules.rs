@@ -0,0 +1,386 @@
+use crate::BingoBoard;
+use std::{
+    fmt,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// The row and column rules of the original game.
+pub const STANDARD: &[WinRule] = &[WinRule::Row, WinRule::Column];
+
+/// A way for a board to win, a board has won as soon as one of its rules is met.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WinRule {
+    /// Any full row.
+    Row,
+    /// Any full column.
+    Column,
+    /// Either full diagonal, only square boards have diagonals.
+    Diagonal,
+    /// All four corners.
+    Corners,
+    /// Every field on the board.
+    Blackout,
+    /// Every field of a user-supplied mask.
+    Pattern(Pattern),
+}
+
+impl WinRule {
+    pub fn is_met(&self, board: &BingoBoard) -> bool {
+        let (height, width) = (board.height(), board.width());
+        if height == 0 || width == 0 {
+            return false;
+        }
+        match self {
+            WinRule::Row => (0..height).any(|r| (0..width).all(|c| board.is_marked(r, c))),
+            WinRule::Column => (0..width).any(|c| (0..height).all(|r| board.is_marked(r, c))),
+            WinRule::Diagonal => {
+                height == width
+                    && ((0..height).all(|i| board.is_marked(i, i))
+                        || (0..height).all(|i| board.is_marked(i, width - 1 - i)))
+            }
+            WinRule::Corners => [
+                (0, 0),
+                (0, width - 1),
+                (height - 1, 0),
+                (height - 1, width - 1),
+            ]
+            .iter()
+            .all(|&(r, c)| board.is_marked(r, c)),
+            WinRule::Blackout => (0..height).all(|r| (0..width).all(|c| board.is_marked(r, c))),
+            WinRule::Pattern(pattern) => pattern.is_met(board),
+        }
+    }
+    /// Every set of fields that meets the rule on a board of the given size, as (row, column).
+    pub fn field_sets(&self, height: usize, width: usize) -> Vec<Vec<(usize, usize)>> {
+        if height == 0 || width == 0 {
+            return Vec::new();
+        }
+        match self {
+            WinRule::Row => (0..height)
+                .map(|r| (0..width).map(|c| (r, c)).collect())
+                .collect(),
+            WinRule::Column => (0..width)
+                .map(|c| (0..height).map(|r| (r, c)).collect())
+                .collect(),
+            WinRule::Diagonal if height != width => Vec::new(),
+            WinRule::Diagonal => vec![
+                (0..height).map(|i| (i, i)).collect(),
+                (0..height).map(|i| (i, width - 1 - i)).collect(),
+            ],
+            WinRule::Corners => vec![vec![
+                (0, 0),
+                (0, width - 1),
+                (height - 1, 0),
+                (height - 1, width - 1),
+            ]],
+            WinRule::Blackout => vec![(0..height)
+                .flat_map(|r| (0..width).map(move |c| (r, c)))
+                .collect()],
+            WinRule::Pattern(pattern) if !pattern.fits(height, width) => Vec::new(),
+            WinRule::Pattern(pattern) => {
+                vec![(0..height)
+                    .flat_map(|r| (0..width).map(move |c| (r, c)))
+                    .filter(|&(r, c)| pattern.mask[r][c])
+                    .collect()]
+            }
+        }
+    }
+    /// Same as `is_met`, but only looks at the lines through the field that was marked last,
+    /// using the mark counters of the board instead of rescanning it. Returns the line that
+    /// was completed.
+    pub fn line_at(&self, board: &BingoBoard, row: usize, column: usize) -> Option<WinLine> {
+        let (height, width) = (board.height(), board.width());
+        match self {
+            WinRule::Row => (board.row_marks[row] == width).then_some(WinLine::Row(row)),
+            WinRule::Column => {
+                (board.column_marks[column] == height).then_some(WinLine::Column(column))
+            }
+            WinRule::Diagonal if height != width => None,
+            WinRule::Diagonal if row == column && board.diagonal_marks[0] == height => {
+                Some(WinLine::Diagonal)
+            }
+            WinRule::Diagonal if row + column + 1 == width && board.diagonal_marks[1] == height => {
+                Some(WinLine::AntiDiagonal)
+            }
+            WinRule::Diagonal => None,
+            WinRule::Corners => {
+                // boards one field high or wide share their corners
+                let corners = height.min(2) * width.min(2);
+                (board.corner_marks == corners).then_some(WinLine::Corners)
+            }
+            WinRule::Blackout => (board.marked == height * width).then_some(WinLine::Blackout),
+            WinRule::Pattern(pattern) => (pattern.fits(height, width)
+                && pattern.requires(row, column)
+                && board.pattern_marks(pattern) == pattern.fields)
+                .then_some(WinLine::Pattern),
+        }
+    }
+}
+
+/// The fields that made a board win.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WinLine {
+    Row(usize),
+    Column(usize),
+    /// From the top left to the bottom right corner.
+    Diagonal,
+    /// From the top right to the bottom left corner.
+    AntiDiagonal,
+    Corners,
+    Blackout,
+    Pattern,
+}
+
+impl fmt::Display for WinLine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WinLine::Row(r) => write!(f, "row {}", r + 1),
+            WinLine::Column(c) => write!(f, "column {}", c + 1),
+            WinLine::Diagonal => write!(f, "diagonal"),
+            WinLine::AntiDiagonal => write!(f, "anti-diagonal"),
+            WinLine::Corners => write!(f, "corners"),
+            WinLine::Blackout => write!(f, "blackout"),
+            WinLine::Pattern => write!(f, "pattern"),
+        }
+    }
+}
+
+/// A mask of fields that all have to be marked, it only applies to boards of the same size.
+///
+/// Patterns are written as one line per row with `X` for a required field and `.` for any
+/// other field, e.g. a "T" on a 5x5 board:
+///
+/// ```text
+/// XXXXX
+/// ..X..
+/// ..X..
+/// ..X..
+/// ..X..
+/// ```
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    mask: Vec<Vec<bool>>,
+    /// Number of required fields.
+    fields: usize,
+    /// Height and width, `None` if the rows aren't all equally long.
+    size: Option<(usize, usize)>,
+    /// Tells the mark counters of a board apart, copies of a pattern share it.
+    id: usize,
+}
+
+/// The id of the next pattern.
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.mask == other.mask
+    }
+}
+
+impl Eq for Pattern {}
+
+impl Pattern {
+    pub fn from_string(s: &str) -> Self {
+        let mask = s
+            .lines()
+            .map(|l| l.trim())
+            .filter(|l| !l.is_empty())
+            .map(|l| {
+                l.chars()
+                    .map(|c| match c {
+                        'X' | 'x' => true,
+                        '.' => false,
+                        _ => panic!("Unexpected character {:?} in pattern.", c),
+                    })
+                    .collect()
+            })
+            .collect::<Vec<Vec<bool>>>();
+        let fields = mask.iter().flatten().filter(|&&required| required).count();
+        let width = mask.first().map_or(0, |row| row.len());
+        let size = mask
+            .iter()
+            .all(|row| row.len() == width)
+            .then_some((mask.len(), width));
+        Self {
+            mask,
+            fields,
+            size,
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+    pub fn id(&self) -> usize {
+        self.id
+    }
+    /// Whether the pattern applies to a board of the given size.
+    pub fn fits(&self, height: usize, width: usize) -> bool {
+        self.size == Some((height, width))
+    }
+    pub fn requires(&self, row: usize, column: usize) -> bool {
+        self.mask
+            .get(row)
+            .and_then(|r| r.get(column))
+            .copied()
+            .unwrap_or(false)
+    }
+    pub fn is_met(&self, board: &BingoBoard) -> bool {
+        self.fits(board.height(), board.width())
+            && self.mask.iter().enumerate().all(|(r, row)| {
+                row.iter()
+                    .enumerate()
+                    .all(|(c, &required)| !required || board.is_marked(r, c))
+            })
+    }
+}
+
+/// Reads several patterns separated by blank lines.
+pub fn parse_patterns(s: &str) -> Vec<Pattern> {
+    let mut patterns = Vec::new();
+    let mut block = String::new();
+    for line in s.lines().chain(std::iter::once("")) {
+        if line.trim().is_empty() {
+            if !block.is_empty() {
+                patterns.push(Pattern::from_string(&block));
+                block.clear();
+            }
+        } else {
+            block.push_str(line);
+            block.push('\n');
+        }
+    }
+    patterns
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BingoField;
+
+    fn board(size: usize) -> BingoBoard {
+        BingoBoard::new(
+            (0..size)
+                .map(|r| (0..size).map(|c| BingoField::new(r * size + c)).collect())
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_diagonal() {
+        let mut b = board(5);
+        for i in [0, 6, 12, 18] {
+            b.mark(i, &[WinRule::Diagonal]);
+        }
+        assert!(!b.won);
+        b.mark(24, &[WinRule::Diagonal]);
+        assert!(b.won);
+
+        let mut b = board(5);
+        for i in [4, 8, 12, 16, 20] {
+            b.mark(i, STANDARD);
+        }
+        assert!(!b.won);
+        assert!(WinRule::Diagonal.is_met(&b));
+    }
+
+    #[test]
+    fn test_corners_and_blackout() {
+        let mut b = board(3);
+        for i in [0, 2, 6] {
+            b.mark(i, &[WinRule::Corners]);
+        }
+        assert!(!b.won);
+        b.mark(8, &[WinRule::Corners]);
+        assert!(b.won);
+        assert!(!WinRule::Blackout.is_met(&b));
+        for i in 0..9 {
+            b.mark(i, STANDARD);
+        }
+        assert!(WinRule::Blackout.is_met(&b));
+    }
+
+    #[test]
+    fn test_incremental_check_matches_rescan() {
+        let rules = [
+            WinRule::Row,
+            WinRule::Column,
+            WinRule::Diagonal,
+            WinRule::Corners,
+            WinRule::Blackout,
+            WinRule::Pattern(Pattern::from_string("X..X\n.XX.\n....\n...X")),
+        ];
+        for rule in rules.iter() {
+            let mut b = board(4);
+            // a fixed shuffle of 0..16
+            for i in (0..16).map(|i| (i * 7 + 3) % 16) {
+                b.mark(i, std::slice::from_ref(rule));
+                assert_eq!(b.won, rule.is_met(&b), "{:?} after {}", rule, i);
+                if b.won {
+                    break;
+                }
+            }
+            assert!(b.won);
+        }
+        // the counters also know about fields marked before the first check
+        let mut b = board(4);
+        b.mark(0, &[]);
+        b.mark(5, &[]);
+        let rules = [WinRule::Row, rules[5].clone()];
+        for i in [3, 6, 10] {
+            b.mark(i, &rules);
+            assert!(!b.won);
+        }
+        b.mark(15, &rules);
+        assert_eq!(b.winning_line(), Some(WinLine::Pattern));
+        // as many rules as before, but a pattern among them, and marks with other rules between
+        let pattern = rules[1].clone();
+        let mut b = board(4);
+        for i in [0, 5] {
+            b.mark(i, STANDARD);
+        }
+        let mixed = [WinRule::Corners, pattern.clone()];
+        b.mark(3, &mixed);
+        b.mark(6, STANDARD);
+        b.mark(10, &mixed);
+        assert!(!b.won);
+        b.mark(15, &mixed);
+        assert!(pattern.is_met(&b));
+        assert_eq!(b.winning_line(), Some(WinLine::Pattern));
+
+        // a single row has only two corners
+        let mut b = BingoBoard::new(vec![(0..4).map(BingoField::new).collect()]);
+        b.mark(0, &[WinRule::Corners]);
+        assert!(!b.won);
+        b.mark(3, &[WinRule::Corners]);
+        assert_eq!(b.winning_line(), Some(WinLine::Corners));
+    }
+
+    #[test]
+    fn test_pattern() {
+        let patterns = parse_patterns(
+            "X...X
+.X.X.
+..X..
+.X.X.
+X...X
+
+XXXXX
+..X..
+..X..
+..X..
+..X..
+",
+        );
+        assert_eq!(patterns.len(), 2);
+        let x = WinRule::Pattern(patterns[0].clone());
+        let t = WinRule::Pattern(patterns[1].clone());
+        let mut b = board(5);
+        for i in [0, 1, 2, 3, 4, 7, 12, 17] {
+            b.mark(i, &[x.clone(), t.clone()]);
+        }
+        assert!(!b.won);
+        b.mark(22, &[x.clone(), t.clone()]);
+        assert!(b.won);
+        assert!(!x.is_met(&b));
+        // patterns only apply to boards of the same size
+        assert!(!t.is_met(&board(4)));
+    }
+}
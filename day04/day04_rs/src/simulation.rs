@@ -0,0 +1,180 @@
+use crate::{play, rules::WinRule, BingoBoard};
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use std::thread;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SimulationOptions {
+    pub trials: usize,
+    /// Trial `n` shuffles with `seed + n`, so results don't depend on the number of threads.
+    pub seed: u64,
+    pub threads: usize,
+}
+
+impl Default for SimulationOptions {
+    fn default() -> Self {
+        Self {
+            trials: 1000,
+            seed: 0,
+            threads: 1,
+        }
+    }
+}
+
+/// How a single board fared over all trials.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoardOdds {
+    /// Share of trials in which the board was among the first winners. Boards winning on the
+    /// same turn all count, so the odds of all boards can add up to more than 1.
+    pub first: f64,
+    /// Share of trials in which the board was among the last winners.
+    pub last: f64,
+    /// Share of trials in which the board won at all.
+    pub wins: f64,
+    /// Average number of drawn numbers until the board won, over the trials it won.
+    pub expected_turns: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Tally {
+    first: usize,
+    last: usize,
+    wins: usize,
+    turns: usize,
+}
+
+/// Plays `options.trials` games, each drawing all of `numbers` in a random order.
+pub fn simulate(
+    boards: &[BingoBoard],
+    numbers: &[usize],
+    rules: &[WinRule],
+    options: SimulationOptions,
+) -> Vec<BoardOdds> {
+    let threads = options.threads.max(1);
+    let tallies = thread::scope(|s| {
+        let handles = (0..threads)
+            .map(|t| {
+                s.spawn(move || {
+                    let mut tallies = vec![Tally::default(); boards.len()];
+                    for trial in (t..options.trials).step_by(threads) {
+                        let seed = options.seed.wrapping_add(trial as u64);
+                        run_trial(boards, numbers, rules, seed, &mut tallies);
+                    }
+                    tallies
+                })
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .map(|h| h.join().unwrap())
+            .collect::<Vec<_>>()
+    });
+
+    let trials = options.trials.max(1) as f64;
+    (0..boards.len())
+        .map(|b| {
+            let total = tallies.iter().fold(Tally::default(), |acc, t| Tally {
+                first: acc.first + t[b].first,
+                last: acc.last + t[b].last,
+                wins: acc.wins + t[b].wins,
+                turns: acc.turns + t[b].turns,
+            });
+            BoardOdds {
+                first: total.first as f64 / trials,
+                last: total.last as f64 / trials,
+                wins: total.wins as f64 / trials,
+                expected_turns: (total.wins > 0).then(|| total.turns as f64 / total.wins as f64),
+            }
+        })
+        .collect()
+}
+
+fn run_trial(
+    boards: &[BingoBoard],
+    numbers: &[usize],
+    rules: &[WinRule],
+    seed: u64,
+    tallies: &mut [Tally],
+) {
+    let mut order = numbers.to_vec();
+    order.shuffle(&mut StdRng::seed_from_u64(seed));
+    let mut boards = boards.to_vec();
+    let timeline = play(&order, &mut boards, rules);
+    let (first_turn, last_turn) = match (timeline.first(), timeline.last()) {
+        (Some(first), Some(last)) => (first.turn, last.turn),
+        _ => return,
+    };
+    for win in timeline.wins.iter() {
+        let tally = &mut tallies[win.board];
+        tally.wins += 1;
+        tally.turns += win.turn;
+        if win.turn == first_turn {
+            tally.first += 1;
+        }
+        if win.turn == last_turn {
+            tally.last += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixtures::board, rules::STANDARD};
+
+    #[test]
+    fn test_simulate() {
+        // 99 is never drawn and lies on every line of the second board
+        let boards = [
+            board(&[&[1, 2], &[3, 4]]),
+            board(&[&[1, 99], &[99, 2]]),
+            board(&[&[7, 8], &[9, 10]]),
+        ];
+        let numbers = (1..=10).collect::<Vec<_>>();
+        let options = SimulationOptions {
+            trials: 200,
+            ..SimulationOptions::default()
+        };
+        let odds = simulate(&boards, &numbers, STANDARD, options);
+        assert_eq!(odds.len(), 3);
+        assert_eq!(odds[1].wins, 0.0);
+        assert_eq!(odds[1].expected_turns, None);
+        assert_eq!(odds[0].wins, 1.0);
+        assert!(odds[0].first > 0.0 && odds[0].last > 0.0);
+        assert!(odds[0].expected_turns.unwrap() >= 2.0);
+        assert!(odds[0].first + odds[2].first >= 1.0);
+        assert!(odds[0].last + odds[2].last >= 1.0);
+    }
+
+    #[test]
+    fn test_simulate_is_reproducible() {
+        let boards = [
+            board(&[&[1, 2], &[3, 4]]),
+            board(&[&[5, 6], &[7, 8]]),
+            board(&[&[1, 5], &[4, 8]]),
+        ];
+        let numbers = (1..=8).collect::<Vec<_>>();
+        let options = SimulationOptions {
+            trials: 100,
+            seed: 42,
+            threads: 1,
+        };
+        let single = simulate(&boards, &numbers, STANDARD, options);
+        let threaded = simulate(
+            &boards,
+            &numbers,
+            STANDARD,
+            SimulationOptions {
+                threads: 4,
+                ..options
+            },
+        );
+        assert_eq!(single, threaded);
+        let other_seed = simulate(
+            &boards,
+            &numbers,
+            STANDARD,
+            SimulationOptions { seed: 7, ..options },
+        );
+        assert_ne!(single, other_seed);
+    }
+}
@@ -0,0 +1,290 @@
+use crate::{rules::WinRule, BingoBoard, WinEvent};
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode},
+    execute, terminal,
+};
+use std::{
+    io::{self, Write},
+    time::Duration,
+};
+
+const MARKED: &str = "\x1b[7m";
+const WON: &str = "\x1b[1;32m";
+const RESET: &str = "\x1b[0m";
+
+/// A game that can be stepped forwards and backwards one drawn number at a time.
+pub struct Caller<'a> {
+    initial: Vec<BingoBoard>,
+    boards: Vec<BingoBoard>,
+    drawn_numbers: &'a [usize],
+    rules: &'a [WinRule],
+    /// Numbers drawn so far.
+    turn: usize,
+    wins: Vec<WinEvent>,
+}
+
+impl<'a> Caller<'a> {
+    pub fn new(boards: Vec<BingoBoard>, drawn_numbers: &'a [usize], rules: &'a [WinRule]) -> Self {
+        Self {
+            initial: boards.clone(),
+            boards,
+            drawn_numbers,
+            rules,
+            turn: 0,
+            wins: Vec::new(),
+        }
+    }
+    /// True once all numbers are drawn or every board has won.
+    pub fn is_over(&self) -> bool {
+        self.turn == self.drawn_numbers.len() || self.wins.len() == self.boards.len()
+    }
+    /// Draws the next number and returns the wins it caused.
+    pub fn advance(&mut self) -> &[WinEvent] {
+        let before = self.wins.len();
+        if let Some(&n) = self.drawn_numbers.get(self.turn) {
+            self.turn += 1;
+            for (b, board) in self.boards.iter_mut().enumerate() {
+                let won = board.won;
+                board.mark(n, self.rules);
+                if !won && board.won {
+                    self.wins.push(WinEvent {
+                        board: b,
+                        turn: self.turn,
+                        called: n,
+                        line: board.winning_line().unwrap(),
+                        score: board.calculate_score(n),
+                    });
+                }
+            }
+        }
+        &self.wins[before..]
+    }
+    /// Takes back the last drawn number by replaying the game up to the turn before.
+    pub fn rewind(&mut self) {
+        let turn = self.turn.saturating_sub(1);
+        self.boards = self.initial.clone();
+        self.turn = 0;
+        self.wins.clear();
+        while self.turn < turn {
+            self.advance();
+        }
+    }
+    /// Draws the boards side by side, as many as fit into `columns` characters.
+    pub fn render(&self, columns: usize) -> String {
+        let mut out = String::new();
+        let called = match self.turn {
+            0 => "-".to_string(),
+            turn => self.drawn_numbers[turn - 1].to_string(),
+        };
+        out.push_str(&format!(
+            "Turn {}/{}  Called: {}\n\n",
+            self.turn,
+            self.drawn_numbers.len(),
+            called
+        ));
+
+        let digits = self
+            .boards
+            .iter()
+            .flat_map(|b| b.rows.iter().flatten())
+            .map(|f| f.val.to_string().len())
+            .max()
+            .unwrap_or(1);
+        let board_width = |b: &BingoBoard| (b.width() * (digits + 1)).max(10);
+        let mut start = 0;
+        while start < self.boards.len() {
+            // fill a band of boards up to the terminal width
+            let mut end = start + 1;
+            let mut used = board_width(&self.boards[start]);
+            while end < self.boards.len() && used + 2 + board_width(&self.boards[end]) <= columns {
+                used += 2 + board_width(&self.boards[end]);
+                end += 1;
+            }
+            let band = start..end;
+            let height = band
+                .clone()
+                .map(|b| self.boards[b].height())
+                .max()
+                .unwrap_or(0);
+            let mut lines = vec![String::new(); height + 1];
+            for b in band {
+                let board = &self.boards[b];
+                let width = board_width(board);
+                let title = format!("Board {}", b + 1);
+                if board.won {
+                    lines[0].push_str(&format!("{}{:<width$}{}  ", WON, title, RESET));
+                } else {
+                    lines[0].push_str(&format!("{:<width$}  ", title));
+                }
+                for (r, line) in lines.iter_mut().skip(1).enumerate() {
+                    let mut text = String::new();
+                    let mut len = 0;
+                    if r < board.height() {
+                        for field in board.rows[r].iter() {
+                            let cell = format!("{:>digits$}", field.val);
+                            if field.marked {
+                                text.push_str(&format!(" {}{}{}", MARKED, cell, RESET));
+                            } else {
+                                text.push_str(&format!(" {}", cell));
+                            }
+                            len += digits + 1;
+                        }
+                    }
+                    line.push_str(&text);
+                    line.push_str(&" ".repeat(width - len + 2));
+                }
+            }
+            for line in lines {
+                out.push_str(line.trim_end());
+                out.push('\n');
+            }
+            out.push('\n');
+            start = end;
+        }
+
+        for win in self.wins.iter().filter(|w| w.turn == self.turn) {
+            out.push_str(&format!(
+                "BINGO! Board {} wins with {} ({}), score {}\n",
+                win.board + 1,
+                win.called,
+                win.line,
+                win.score
+            ));
+        }
+        out.push_str(&format!(
+            "{} of {} boards have won\n",
+            self.wins.len(),
+            self.boards.len()
+        ));
+        out
+    }
+}
+
+/// Runs the caller in the terminal until the user quits. Without an interval the game only
+/// advances on a key press.
+pub fn run(caller: &mut Caller, interval: Option<Duration>) -> io::Result<()> {
+    let mut stdout = io::stdout();
+    terminal::enable_raw_mode()?;
+    execute!(stdout, terminal::EnterAlternateScreen, cursor::Hide)?;
+    let result = event_loop(caller, interval, &mut stdout);
+    execute!(stdout, terminal::LeaveAlternateScreen, cursor::Show)?;
+    terminal::disable_raw_mode()?;
+    result
+}
+
+fn event_loop(
+    caller: &mut Caller,
+    interval: Option<Duration>,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let mut paused = interval.is_none();
+    let interval = interval.unwrap_or(Duration::from_secs(1));
+    loop {
+        let (columns, _) = terminal::size()?;
+        let status = if paused { "paused" } else { "running" };
+        let screen = format!(
+            "{}\n[space] next  [backspace] back  [p] pause/resume ({})  [q] quit\n",
+            caller.render(columns as usize),
+            status
+        );
+        execute!(
+            out,
+            terminal::Clear(terminal::ClearType::All),
+            cursor::MoveTo(0, 0)
+        )?;
+        // raw mode doesn't return to the start of the line on its own
+        write!(out, "{}", screen.replace('\n', "\r\n"))?;
+        out.flush()?;
+
+        let timeout = if paused {
+            Duration::from_secs(3600)
+        } else {
+            interval
+        };
+        if !event::poll(timeout)? {
+            if !paused {
+                caller.advance();
+                paused = caller.is_over();
+            }
+            continue;
+        }
+        if let Event::Key(key) = event::read()? {
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Char(' ') | KeyCode::Enter | KeyCode::Right => {
+                    caller.advance();
+                }
+                KeyCode::Backspace | KeyCode::Left => caller.rewind(),
+                KeyCode::Char('p') => paused = !paused,
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_puzzle_data, play, rules::STANDARD};
+
+    static EXAMPLE_DATA: &str = "5,1,2,7,3,4
+
+1 2
+3 4
+
+5 6
+7 8";
+
+    #[test]
+    fn test_advance_and_rewind() {
+        let (drawn_numbers, boards) = parse_puzzle_data(EXAMPLE_DATA);
+        let mut caller = Caller::new(boards, &drawn_numbers, STANDARD);
+        assert!(caller.advance().is_empty());
+        assert!(caller.advance().is_empty());
+        let wins = caller.advance().to_vec();
+        assert_eq!(wins.len(), 1);
+        assert_eq!((wins[0].board, wins[0].score), (0, 7 * 2));
+
+        caller.rewind();
+        assert_eq!(caller.turn, 2);
+        assert!(caller.wins.is_empty());
+        while !caller.is_over() {
+            caller.advance();
+        }
+        // stepping through gives the same result as playing the whole game
+        let (_, mut boards) = parse_puzzle_data(EXAMPLE_DATA);
+        assert_eq!(
+            caller.wins,
+            play(&drawn_numbers, &mut boards, STANDARD).wins
+        );
+        assert!(caller.advance().is_empty());
+    }
+
+    #[test]
+    fn test_render() {
+        let (drawn_numbers, boards) = parse_puzzle_data(EXAMPLE_DATA);
+        let mut caller = Caller::new(boards, &drawn_numbers, STANDARD);
+        assert_eq!(
+            caller.render(80),
+            "Turn 0/6  Called: -
+
+Board 1     Board 2
+ 1 2         5 6
+ 3 4         7 8
+
+0 of 2 boards have won
+"
+        );
+        caller.advance();
+        caller.advance();
+        caller.advance();
+        let screen = caller.render(80);
+        assert!(screen.starts_with("Turn 3/6  Called: 2\n"));
+        assert!(screen.contains(&format!("{}Board 1   {}", WON, RESET)));
+        assert!(screen.contains(&format!(" {}1{} {}2{}", MARKED, RESET, MARKED, RESET)));
+        assert!(screen.contains("BINGO! Board 1 wins with 2 (row 1), score 14\n"));
+        // boards move to the next band when they don't fit
+        assert!(caller.render(12).contains("\n\nBoard 2\n"));
+    }
+}
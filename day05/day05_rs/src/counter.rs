@@ -0,0 +1,242 @@
+use crate::{Line, Point};
+use std::collections::HashMap;
+
+/// Largest bounding box, in cells, that gets a dense grid.
+pub const DENSE_LIMIT: u64 = 1 << 24;
+
+/// How many points are covered exactly `n` times, for every `n`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Histogram {
+    /// `points[n]` points are covered `n` times, uncovered points are not counted.
+    points: Vec<usize>,
+}
+
+impl Histogram {
+    /// Builds the histogram from the coverage of every point, zeros are skipped.
+    pub fn from_coverage(coverage: impl Iterator<Item = usize>) -> Self {
+        let mut points = Vec::new();
+        for n in coverage.filter(|&n| n > 0) {
+            if n >= points.len() {
+                points.resize(n + 1, 0);
+            }
+            points[n] += 1;
+        }
+        Self { points }
+    }
+    pub fn exactly(&self, n: usize) -> usize {
+        match n {
+            0 => 0,
+            n => self.points.get(n).copied().unwrap_or(0),
+        }
+    }
+    /// Points covered at least `n` times, `at_least(2)` being the puzzle answer.
+    pub fn at_least(&self, n: usize) -> usize {
+        self.points.iter().skip(n.max(1)).sum()
+    }
+    /// The highest coverage of any point.
+    pub fn max_coverage(&self) -> usize {
+        self.points.len().saturating_sub(1)
+    }
+    /// `(coverage, points)` for every coverage some point has, from 1 up.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.points
+            .iter()
+            .copied()
+            .enumerate()
+            .filter(|&(_, points)| points > 0)
+    }
+}
+
+/// Counts how often every point is covered.
+pub trait PointCounter {
+    fn add(&mut self, point: Point);
+    fn histogram(&self) -> Histogram;
+    /// Number of points covered at least twice.
+    fn overlaps(&self) -> usize {
+        self.histogram().at_least(2)
+    }
+    /// Whether the counts are kept in a grid over the bounding box.
+    fn is_dense(&self) -> bool {
+        false
+    }
+}
+
+/// A map from point to count, for lines spread over a large area.
+#[derive(Debug, Default)]
+pub struct SparseCounter {
+    counts: HashMap<Point, u32>,
+}
+
+impl SparseCounter {
+    /// Every covered point with its count, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (Point, u32)> + '_ {
+        self.counts.iter().map(|(&point, &count)| (point, count))
+    }
+}
+
+impl PointCounter for SparseCounter {
+    fn add(&mut self, point: Point) {
+        *self.counts.entry(point).or_insert(0) += 1;
+    }
+    fn histogram(&self) -> Histogram {
+        Histogram::from_coverage(self.counts.values().map(|&n| n as usize))
+    }
+}
+
+/// A grid over the bounding box of the lines, for the usual small inputs.
+#[derive(Debug)]
+pub struct DenseCounter {
+    min: Point,
+    width: usize,
+    counts: Vec<u16>,
+}
+
+impl DenseCounter {
+    pub fn new(min: Point, max: Point) -> Self {
+        let width = (max.x as i64 - min.x as i64 + 1) as usize;
+        let height = (max.y as i64 - min.y as i64 + 1) as usize;
+        Self {
+            min,
+            width,
+            counts: vec![0; width * height],
+        }
+    }
+    fn index(&self, point: Point) -> usize {
+        let x = (point.x as i64 - self.min.x as i64) as usize;
+        let y = (point.y as i64 - self.min.y as i64) as usize;
+        y * self.width + x
+    }
+    /// The counts row by row, from the smallest `y` up.
+    pub fn rows(&self) -> impl Iterator<Item = &[u16]> {
+        self.counts.chunks(self.width)
+    }
+}
+
+impl PointCounter for DenseCounter {
+    fn add(&mut self, point: Point) {
+        let i = self.index(point);
+        let count = &mut self.counts[i];
+        *count = count.saturating_add(1);
+    }
+    /// Counts above `u16::MAX` are capped there.
+    fn histogram(&self) -> Histogram {
+        Histogram::from_coverage(self.counts.iter().map(|&n| n as usize))
+    }
+    fn is_dense(&self) -> bool {
+        true
+    }
+}
+
+/// Adds every point of every line to the counter.
+pub fn tally(counter: &mut dyn PointCounter, lines: &[Line]) {
+    for line in lines {
+        for point in line.points() {
+            counter.add(point);
+        }
+    }
+}
+
+/// The smallest and largest corner of the area covered by the lines.
+pub fn bounding_box(lines: &[Line]) -> Option<(Point, Point)> {
+    let mut points = lines.iter().flat_map(|l| [l.start, l.end]);
+    let first = points.next()?;
+    Some(points.fold((first, first), |(min, max), p| {
+        (
+            Point::new(min.x.min(p.x), min.y.min(p.y)),
+            Point::new(max.x.max(p.x), max.y.max(p.y)),
+        )
+    }))
+}
+
+pub fn fits_dense(min: Point, max: Point) -> bool {
+    let width = (max.x as i64 - min.x as i64 + 1) as u64;
+    let height = (max.y as i64 - min.y as i64 + 1) as u64;
+    width.saturating_mul(height) <= DENSE_LIMIT
+}
+
+/// Picks the dense grid when the bounding box of the lines is small enough.
+pub fn counter_for(lines: &[Line]) -> Box<dyn PointCounter> {
+    match bounding_box(lines) {
+        Some((min, max)) if fits_dense(min, max) => Box::new(DenseCounter::new(min, max)),
+        _ => Box::new(SparseCounter::default()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::lines;
+
+    fn count(counter: &mut dyn PointCounter, lines: &[Line]) -> usize {
+        tally(counter, lines);
+        counter.overlaps()
+    }
+
+    #[test]
+    fn test_counters_agree() {
+        let star = lines("-3,-3 -> 3,3\n-3,3 -> 3,-3\n0,-5 -> 0,5\n-4,0 -> 4,0\n1,1 -> 2,2");
+        let (min, max) = bounding_box(&star).unwrap();
+        assert_eq!((min, max), (Point::new(-4, -5), Point::new(4, 5)));
+        let dense = count(&mut DenseCounter::new(min, max), &star);
+        let sparse = count(&mut SparseCounter::default(), &star);
+        assert_eq!(dense, 3);
+        assert_eq!(dense, sparse);
+        let mut grid = DenseCounter::new(min, max);
+        tally(&mut grid, &star);
+        let mut map = SparseCounter::default();
+        tally(&mut map, &star);
+        assert_eq!(grid.histogram(), map.histogram());
+
+        let mut grid = DenseCounter::new(Point::new(0, 0), Point::new(2, 1));
+        tally(&mut grid, &lines("0,0 -> 2,0\n1,0 -> 1,1"));
+        assert_eq!(
+            grid.rows().collect::<Vec<_>>(),
+            vec![&[1, 2, 1], &[0, 1, 0]]
+        );
+    }
+
+    #[test]
+    fn test_histogram() {
+        let histogram = Histogram::from_coverage([1, 0, 3, 1, 1, 3, 0, 2].into_iter());
+        assert_eq!(
+            histogram.iter().collect::<Vec<_>>(),
+            vec![(1, 3), (2, 1), (3, 2)]
+        );
+        assert_eq!(histogram.exactly(1), 3);
+        assert_eq!(histogram.exactly(0), 0);
+        assert_eq!(histogram.exactly(7), 0);
+        assert_eq!(histogram.at_least(0), 6);
+        assert_eq!(histogram.at_least(2), 3);
+        assert_eq!(histogram.at_least(4), 0);
+        assert_eq!(histogram.max_coverage(), 3);
+        assert_eq!(Histogram::default().max_coverage(), 0);
+    }
+
+    #[test]
+    fn test_counter_for() {
+        let small = lines("0,0 -> 999,999");
+        let mut counter = counter_for(&small);
+        assert!(counter.is_dense());
+        counter.add(Point::new(999, 999));
+        counter.add(Point::new(999, 999));
+        assert_eq!(counter.overlaps(), 1);
+        assert!(fits_dense(Point::new(0, 0), Point::new(999, 999)));
+        // a dense grid over this box would need gigabytes
+        let spread = lines("-100000,-100000 -> 100000,100000");
+        assert!(!fits_dense(
+            Point::new(-100000, -100000),
+            Point::new(100000, 100000)
+        ));
+        assert!(!fits_dense(
+            Point::new(i32::MIN, i32::MIN),
+            Point::new(i32::MAX, i32::MAX)
+        ));
+        let mut counter = counter_for(&spread);
+        assert!(!counter.is_dense());
+        counter.add(Point::new(5, 5));
+        counter.add(Point::new(5, 5));
+        assert_eq!(counter.overlaps(), 1);
+        assert!(!counter_for(&[]).is_dense());
+        assert!(bounding_box(&[]).is_none());
+    }
+}
@@ -0,0 +1,164 @@
+use crate::{Line, Point};
+use std::collections::{BTreeMap, HashMap};
+
+/// Two lines and the points they share, more than one if they overlap.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Intersection {
+    pub a: usize,
+    pub b: usize,
+    pub points: Vec<Point>,
+}
+
+/// The lowest and the highest corner of the rectangle between two opposite corners.
+pub fn corners(a: Point, b: Point) -> (Point, Point) {
+    (
+        Point::new(a.x.min(b.x), a.y.min(b.y)),
+        Point::new(a.x.max(b.x), a.y.max(b.y)),
+    )
+}
+
+/// The lines passing through every covered point, rasterized once so that queries don't have
+/// to look at the lines again. Lines are referred to by their position in the input.
+pub struct LineIndex<'a> {
+    lines: &'a [Line],
+    /// Line positions by `y`, then `x`.
+    rows: BTreeMap<i32, BTreeMap<i32, Vec<usize>>>,
+}
+
+impl<'a> LineIndex<'a> {
+    pub fn new(lines: &'a [Line]) -> Self {
+        let mut rows: BTreeMap<i32, BTreeMap<i32, Vec<usize>>> = BTreeMap::new();
+        for (i, line) in lines.iter().enumerate() {
+            for point in line.points() {
+                rows.entry(point.y)
+                    .or_default()
+                    .entry(point.x)
+                    .or_default()
+                    .push(i);
+            }
+        }
+        Self { lines, rows }
+    }
+    pub fn line(&self, i: usize) -> &'a Line {
+        &self.lines[i]
+    }
+    /// The lines passing through `point`, in input order.
+    pub fn at(&self, point: Point) -> &[usize] {
+        self.rows
+            .get(&point.y)
+            .and_then(|row| row.get(&point.x))
+            .map_or(&[], |lines| lines.as_slice())
+    }
+    /// The covered points inside the rectangle between two opposite corners, given in any
+    /// order, row by row with the lines passing through them.
+    pub fn in_rect(&self, a: Point, b: Point) -> impl Iterator<Item = (Point, &[usize])> {
+        let (min, max) = corners(a, b);
+        self.rows.range(min.y..=max.y).flat_map(move |(&y, row)| {
+            row.range(min.x..=max.x)
+                .map(move |(&x, lines)| (Point::new(x, y), lines.as_slice()))
+        })
+    }
+    /// Every line passing through the rectangle, in input order.
+    pub fn lines_in_rect(&self, a: Point, b: Point) -> Vec<usize> {
+        let mut lines = self
+            .in_rect(a, b)
+            .flat_map(|(_, lines)| lines.iter().copied())
+            .collect::<Vec<_>>();
+        lines.sort_unstable();
+        lines.dedup();
+        lines
+    }
+    /// Every pair of lines sharing at least one point, ordered by the lines. The points of
+    /// each pair are ordered row by row.
+    pub fn intersections(&self) -> Vec<Intersection> {
+        let mut pairs: HashMap<(usize, usize), Vec<Point>> = HashMap::new();
+        for (&y, row) in self.rows.iter() {
+            for (&x, lines) in row.iter().filter(|(_, lines)| lines.len() >= 2) {
+                for (i, &a) in lines.iter().enumerate() {
+                    for &b in lines[i + 1..].iter() {
+                        pairs.entry((a, b)).or_default().push(Point::new(x, y));
+                    }
+                }
+            }
+        }
+        let mut intersections = pairs
+            .into_iter()
+            .map(|((a, b), points)| Intersection { a, b, points })
+            .collect::<Vec<_>>();
+        intersections.sort_unstable_by_key(|i| (i.a, i.b));
+        intersections
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{lines, EXAMPLE_DATA};
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_at() {
+        let lines = lines(EXAMPLE_DATA);
+        let index = LineIndex::new(&lines);
+        assert_eq!(index.at(Point::new(4, 4)), &[1, 2, 8]);
+        assert_eq!(index.at(Point::new(7, 4)), &[2, 4]);
+        assert_eq!(index.at(Point::new(1, 0)), &[] as &[usize]);
+        assert_eq!(index.at(Point::new(-100, 50)), &[] as &[usize]);
+        assert_eq!(index.line(4).points().first(), Some(&Point::new(7, 0)));
+    }
+
+    #[test]
+    fn test_in_rect() {
+        let lines = lines(EXAMPLE_DATA);
+        let index = LineIndex::new(&lines);
+        assert_eq!(
+            index
+                .in_rect(Point::new(1, 3), Point::new(3, 4))
+                .collect::<Vec<_>>(),
+            vec![
+                (Point::new(3, 3), &[8][..]),
+                (Point::new(1, 4), &[7][..]),
+                (Point::new(2, 4), &[7][..]),
+                (Point::new(3, 4), &[2, 7][..]),
+            ]
+        );
+        assert_eq!(
+            index.lines_in_rect(Point::new(1, 3), Point::new(3, 4)),
+            vec![2, 7, 8]
+        );
+        assert_eq!(
+            index.lines_in_rect(Point::new(0, 0), Point::new(9, 9)),
+            (0..10).collect::<Vec<_>>()
+        );
+        // the corners may come in any order
+        for (a, b) in [((3, 4), (1, 3)), ((1, 4), (3, 3)), ((3, 3), (1, 4))] {
+            let (a, b) = (Point::new(a.0, a.1), Point::new(b.0, b.1));
+            assert_eq!(corners(a, b), (Point::new(1, 3), Point::new(3, 4)));
+            assert_eq!(index.in_rect(a, b).count(), 4);
+            assert_eq!(index.lines_in_rect(a, b), vec![2, 7, 8]);
+        }
+    }
+
+    #[test]
+    fn test_intersections() {
+        let lines = lines(EXAMPLE_DATA);
+        let index = LineIndex::new(&lines);
+        let intersections = index.intersections();
+        // the two collinear lines along y = 9 overlap in three points
+        assert_eq!(
+            intersections[0],
+            Intersection {
+                a: 0,
+                b: 6,
+                points: vec![Point::new(0, 9), Point::new(1, 9), Point::new(2, 9)]
+            }
+        );
+        assert!(intersections.iter().all(|i| i.a < i.b));
+        let points = intersections
+            .iter()
+            .flat_map(|i| i.points.iter())
+            .collect::<HashSet<_>>();
+        assert_eq!(points.len(), crate::overlaps(&lines));
+        assert!(points.iter().all(|&&p| index.at(p).len() >= 2));
+    }
+}
@@ -1,5 +1,14 @@
-use std::{collections::HashMap, fs, iter::repeat};
+use counter::{counter_for, Histogram, PointCounter};
+use index::LineIndex;
+use render::VentMap;
+use std::{fmt, fs, path::Path};
 
+mod counter;
+mod index;
+mod render;
+mod sweep;
+
+/// A point anywhere on the `i32` plane, coordinates can be negative.
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub struct Point {
     x: i32,
@@ -18,73 +27,366 @@ impl Point {
         }
     }
 }
+impl fmt::Display for Point {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{},{}", self.x, self.y)
+    }
+}
+/// How a line is turned into points.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Raster {
+    /// One cell per step along the longer axis, the nearest to the line, using Bresenham's
+    /// algorithm.
+    Bresenham,
+    /// Only the points with integer coordinates exactly on the line.
+    Lattice,
+}
+
+/// How overlapping points are counted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    /// Count every point of every line, see `counter::counter_for`.
+    Rasterize(Raster),
+    /// Work from the end points of the lines only, see `sweep::overlaps`.
+    Sweep,
+}
+
+/// The direction of a line, single points count as horizontal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Horizontal,
+    Vertical,
+    /// At 45 degrees.
+    Diagonal,
+    /// Any other slope.
+    Other,
+}
+
+/// The lines counted in part 1.
+pub const STRAIGHT: &[Kind] = &[Kind::Horizontal, Kind::Vertical];
+/// The lines counted in part 2.
+pub const ALL: &[Kind] = &[
+    Kind::Horizontal,
+    Kind::Vertical,
+    Kind::Diagonal,
+    Kind::Other,
+];
+
 #[derive(Debug)]
 pub struct Line {
     start: Point,
     end: Point,
-    points: Vec<Point>,
+    raster: Raster,
 }
 
 impl Line {
-    pub fn new(start: Point, end: Point, points: Vec<Point>) -> Self {
-        Self { start, end, points }
+    pub fn new(start: Point, end: Point, raster: Raster) -> Self {
+        Self { start, end, raster }
     }
-    pub fn is_straight(&self) -> bool {
-        self.start.x == self.end.x || self.start.y == self.end.y
+    /// The points of the line, they are only computed when asked for.
+    pub fn points(&self) -> Vec<Point> {
+        Line::rasterize(self.start, self.end, self.raster)
     }
-    pub fn range(a: i32, b: i32) -> Vec<i32> {
-        match a.cmp(&b) {
-            std::cmp::Ordering::Less => (a..=b).collect(),
-            std::cmp::Ordering::Equal => repeat(a).take(1024).collect(),
-            std::cmp::Ordering::Greater => (b..=a.abs()).rev().collect(),
+    pub fn kind(&self) -> Kind {
+        let dx = (self.end.x as i64 - self.start.x as i64).abs();
+        let dy = (self.end.y as i64 - self.start.y as i64).abs();
+        if dy == 0 {
+            Kind::Horizontal
+        } else if dx == 0 {
+            Kind::Vertical
+        } else if dx == dy {
+            Kind::Diagonal
+        } else {
+            Kind::Other
+        }
+    }
+    /// Both rasterizations agree for horizontal, vertical and 45 degree lines. The arithmetic
+    /// is done in `i64`, so lines may span the whole `i32` range.
+    pub fn rasterize(start: Point, end: Point, raster: Raster) -> Vec<Point> {
+        let (x0, y0) = (start.x as i64, start.y as i64);
+        let (x1, y1) = (end.x as i64, end.y as i64);
+        let (dx, dy) = (x1 - x0, y1 - y0);
+        match raster {
+            Raster::Bresenham => {
+                let (sx, sy) = (dx.signum(), dy.signum());
+                let (dx, dy) = (dx.abs(), -dy.abs());
+                let mut points = Vec::with_capacity((dx.max(-dy) + 1) as usize);
+                let (mut x, mut y) = (x0, y0);
+                let mut err = dx + dy;
+                loop {
+                    points.push(Point::new(x as i32, y as i32));
+                    if x == x1 && y == y1 {
+                        break;
+                    }
+                    let e2 = 2 * err;
+                    if e2 >= dy {
+                        err += dy;
+                        x += sx;
+                    }
+                    if e2 <= dx {
+                        err += dx;
+                        y += sy;
+                    }
+                }
+                points
+            }
+            Raster::Lattice => {
+                let steps = gcd(dx.abs(), dy.abs());
+                if steps == 0 {
+                    return vec![start];
+                }
+                let (sx, sy) = (dx / steps, dy / steps);
+                (0..=steps)
+                    .map(|i| Point::new((x0 + i * sx) as i32, (y0 + i * sy) as i32))
+                    .collect()
+            }
         }
     }
     pub fn from_string(s: &str) -> Self {
+        Line::from_string_with(s, Raster::Bresenham)
+    }
+    pub fn from_string_with(s: &str, raster: Raster) -> Self {
         let parts = s.split(" -> ").collect::<Vec<_>>();
         let start = Point::from_string(parts[0]);
         let end = Point::from_string(parts[1]);
-        let points = Line::range(start.x, end.x)
-            .iter()
-            .zip(Line::range(start.y, end.y))
-            .map(|(&x, y)| Point::new(x, y))
-            .collect();
 
-        Self { start, end, points }
+        Self { start, end, raster }
+    }
+}
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} -> {}", self.start, self.end)
+    }
+}
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
     }
 }
 fn main() {
     let input = fs::read_to_string("input.txt").unwrap();
-    let part1 = solve1(&input);
-    println!("Part 1: {}", &part1);
-    let part2 = solve2(&input);
-    println!("Part 2: {}", &part2);
+    let mut lattice = false;
+    let mut sweep = false;
+    let mut check = false;
+    let mut ascii = false;
+    let mut heatmap = None;
+    let mut threshold = 1;
+    let mut trace = None;
+    let mut area = None;
+    let mut show_histogram = false;
+    let mut kinds = ALL.to_vec();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().expect("missing argument value");
+        match arg.as_str() {
+            "--lattice" => lattice = true,
+            // count analytically, only lattice points of lines that aren't at 45 degrees
+            "--sweep" => sweep = true,
+            // compare the sweep with rasterizing the lattice points, sweeping as well
+            "--check" => {
+                check = true;
+                sweep = true;
+            }
+            // draw the diagram of all lines like the puzzle text
+            "--ascii" => ascii = true,
+            // write a PGM or PNG heatmap of all lines, depending on the extension
+            "--heatmap" => heatmap = Some(value()),
+            // only show cells covered at least this often
+            "--threshold" => threshold = value().parse().unwrap(),
+            "--histogram" => show_histogram = true,
+            // the kinds of lines to draw, trace and count, any of h, v, d and o
+            "--kinds" => {
+                kinds = value()
+                    .chars()
+                    .map(|c| match c {
+                        'h' => Kind::Horizontal,
+                        'v' => Kind::Vertical,
+                        'd' => Kind::Diagonal,
+                        'o' => Kind::Other,
+                        _ => panic!("Unknown line kind {}", c),
+                    })
+                    .collect()
+            }
+            // list the lines through a point, or through and crossing inside a rectangle
+            "--trace" => trace = Some(Point::from_string(&value())),
+            "--area" => area = Some((Point::from_string(&value()), Point::from_string(&value()))),
+            _ => panic!("Unknown argument {}", arg),
+        }
+    }
+    if sweep {
+        let part1 = solve1_with(&input, Method::Sweep);
+        println!("Part 1: {}", &part1);
+        let part2 = solve2_with(&input, Method::Sweep);
+        println!("Part 2: {}", &part2);
+        if check {
+            let lattice = (
+                solve1_with(&input, Method::Rasterize(Raster::Lattice)),
+                solve2_with(&input, Method::Rasterize(Raster::Lattice)),
+            );
+            if lattice == (part1, part2) {
+                println!("The sweep matches the rasterized lattice points.");
+            } else {
+                println!("The rasterized lattice points give {:?}.", lattice);
+            }
+        }
+    } else {
+        let part1 = solve1(&input);
+        println!("Part 1: {}", &part1);
+        let part2 = solve2(&input);
+        println!("Part 2: {}", &part2);
+    }
+    if lattice {
+        let lattice = solve2_with(&input, Method::Rasterize(Raster::Lattice));
+        println!("Part 2 (lattice points only): {}", &lattice);
+    }
+    if ascii || heatmap.is_some() {
+        let lines = parse_lines(&input, Method::Rasterize(Raster::Bresenham), &kinds);
+        let map = match VentMap::new(&lines) {
+            Some(map) => map,
+            None => {
+                eprintln!("There are no lines to draw.");
+                std::process::exit(1);
+            }
+        };
+        if map.scale() > 1 {
+            eprintln!(
+                "The map is scaled down to {}x{} cells of {} by {} points.",
+                map.width(),
+                map.height(),
+                map.scale(),
+                map.scale()
+            );
+        }
+        if ascii {
+            if map.scale() > 1 || map.width() > render::ASCII_LIMIT {
+                eprintln!(
+                    "The map is {}x{} cells, use --heatmap instead.",
+                    map.width(),
+                    map.height()
+                );
+            } else {
+                print!("{}", map.to_ascii(threshold));
+            }
+        }
+        if let Some(path) = heatmap {
+            if let Err(error) = map.save(Path::new(&path), threshold) {
+                eprintln!("Couldn't write {}: {}", path, error);
+                std::process::exit(1);
+            }
+        }
+    }
+    if show_histogram {
+        let lines = parse_lines(&input, Method::Rasterize(Raster::Bresenham), &kinds);
+        let counter = tally(&lines);
+        let histogram = counter.histogram();
+        for (coverage, points) in histogram.iter() {
+            println!("{:>4} lines: {:>7} points", coverage, points);
+        }
+        println!(
+            "Covered at least {} times: {}",
+            threshold,
+            histogram.at_least(threshold as usize)
+        );
+        let kind = if counter.is_dense() {
+            "a grid"
+        } else {
+            "a map"
+        };
+        println!("Counted in {} of points.", kind);
+    }
+    if trace.is_some() || area.is_some() {
+        let lines = parse_lines(&input, Method::Rasterize(Raster::Bresenham), &kinds);
+        let index = LineIndex::new(&lines);
+        if let Some(point) = trace {
+            println!("{} lies on {} lines", point, index.at(point).len());
+            for &i in index.at(point) {
+                println!("  line {}: {}", i + 1, index.line(i));
+            }
+        }
+        if let Some((a, b)) = area {
+            let (min, max) = index::corners(a, b);
+            let inside =
+                |p: &Point| (min.x..=max.x).contains(&p.x) && (min.y..=max.y).contains(&p.y);
+            println!("Lines through {} to {}:", min, max);
+            for i in index.lines_in_rect(min, max) {
+                println!("  line {}: {}", i + 1, index.line(i));
+            }
+            println!("Intersections:");
+            for intersection in index.intersections() {
+                let points = intersection
+                    .points
+                    .iter()
+                    .filter(|p| inside(p))
+                    .map(|p| p.to_string())
+                    .collect::<Vec<_>>();
+                if !points.is_empty() {
+                    println!(
+                        "  lines {} and {} at {}",
+                        intersection.a + 1,
+                        intersection.b + 1,
+                        points.join(" ")
+                    );
+                }
+            }
+        }
+    }
+}
+fn tally(lines: &[Line]) -> Box<dyn PointCounter> {
+    let mut counter = counter_for(lines);
+    counter::tally(counter.as_mut(), lines);
+    counter
 }
 pub fn overlaps(lines: &[Line]) -> usize {
-    let mut map = HashMap::with_capacity(lines.len());
+    tally(lines).overlaps()
+}
+/// How many points are covered once, twice and so on.
+pub fn histogram(lines: &[Line]) -> Histogram {
+    tally(lines).histogram()
+}
 
-    for point in lines.iter().clone().flat_map(|l| l.points.clone()) {
-        let counter = map.entry(point).or_insert(0);
-        *counter += 1;
+pub fn count_overlaps(lines: &[Line], method: Method) -> usize {
+    match method {
+        Method::Rasterize(_) => overlaps(lines),
+        Method::Sweep => sweep::overlaps(lines),
     }
-    map.iter().filter(|(_, &am)| am >= 2).count()
+}
+/// The lines of the input of the given kinds.
+fn parse_lines(input: &str, method: Method, kinds: &[Kind]) -> Vec<Line> {
+    let raster = match method {
+        Method::Rasterize(raster) => raster,
+        Method::Sweep => Raster::Lattice,
+    };
+    input
+        .lines()
+        .map(|l| Line::from_string_with(l, raster))
+        .filter(|l| kinds.contains(&l.kind()))
+        .collect()
 }
 
 pub fn solve1(input: &str) -> usize {
-    let straight_lines = input
-        .lines()
-        .map(Line::from_string)
-        .filter(|l| l.is_straight())
-        .collect::<Vec<_>>();
-    overlaps(&straight_lines)
+    solve1_with(input, Method::Rasterize(Raster::Bresenham))
+}
+pub fn solve1_with(input: &str, method: Method) -> usize {
+    solve_with(input, method, STRAIGHT)
 }
 pub fn solve2(input: &str) -> usize {
-    let lines = input.lines().map(Line::from_string).collect::<Vec<_>>();
-    overlaps(&lines)
+    solve2_with(input, Method::Rasterize(Raster::Bresenham))
+}
+pub fn solve2_with(input: &str, method: Method) -> usize {
+    solve_with(input, method, ALL)
+}
+/// Counts the overlaps of the lines of the given kinds only.
+pub fn solve_with(input: &str, method: Method, kinds: &[Kind]) -> usize {
+    count_overlaps(&parse_lines(input, method, kinds), method)
 }
 #[cfg(test)]
-mod tests {
-    use super::*;
-    static EXAMPLE_DATA: &str = "0,9 -> 5,9
+mod fixtures {
+    use super::{Line, Raster};
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    pub static EXAMPLE_DATA: &str = "0,9 -> 5,9
 8,0 -> 0,8
 9,4 -> 3,4
 2,2 -> 2,1
@@ -95,12 +397,188 @@ mod tests {
 0,0 -> 8,8
 5,5 -> 8,2";
 
+    pub fn lines(input: &str) -> Vec<Line> {
+        lines_with(input, Raster::Bresenham)
+    }
+    pub fn lines_with(input: &str, raster: Raster) -> Vec<Line> {
+        input
+            .lines()
+            .map(|l| Line::from_string_with(l, raster))
+            .collect()
+    }
+    /// Seeded random numbers, `range` of them centred on zero.
+    pub fn random(seed: u64) -> impl FnMut(i64) -> i64 {
+        let mut rng = StdRng::seed_from_u64(seed);
+        move |range| rng.gen_range(0..range) - range / 2
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::EXAMPLE_DATA;
+
     #[test]
     fn test_point_fromstring() {
         let input = "100,500";
         assert_eq!(Point::from_string(input), Point::new(100, 500));
         assert_eq!(Point::from_string("100 , 500"), Point::new(100, 500));
     }
+    #[test]
+    fn test_rasterize() {
+        let diagonal = Line::from_string("5,5 -> 8,2");
+        assert_eq!(
+            diagonal.points(),
+            vec![
+                Point::new(5, 5),
+                Point::new(6, 4),
+                Point::new(7, 3),
+                Point::new(8, 2)
+            ]
+        );
+        let long = Line::from_string("0,7 -> 5000,7");
+        assert_eq!(long.points().len(), 5001);
+        assert_eq!(long.points().last(), Some(&Point::new(5000, 7)));
+        assert_eq!(
+            Line::from_string("3,3 -> 3,3").points(),
+            vec![Point::new(3, 3)]
+        );
+
+        let steep = Line::from_string("0,0 -> 2,6");
+        assert_eq!(steep.points().len(), 7);
+        assert_eq!(steep.points().first(), Some(&Point::new(0, 0)));
+        assert_eq!(steep.points().last(), Some(&Point::new(2, 6)));
+        // every step moves by at most one in each direction
+        assert!(steep
+            .points()
+            .windows(2)
+            .all(|w| (w[1].x - w[0].x).abs() <= 1 && (w[1].y - w[0].y).abs() == 1));
+
+        let lattice = Line::from_string_with("0,0 -> 2,6", Raster::Lattice);
+        assert_eq!(
+            lattice.points(),
+            vec![Point::new(0, 0), Point::new(1, 3), Point::new(2, 6)]
+        );
+        assert_eq!(
+            Line::from_string_with("0,9 -> 5,9", Raster::Lattice).points(),
+            Line::from_string("0,9 -> 5,9").points()
+        );
+    }
+
+    #[test]
+    fn test_negative_coordinates() {
+        assert_eq!(Point::from_string("-3,-12"), Point::new(-3, -12));
+        assert_eq!(
+            Line::from_string("-2,1 -> 1,1").points(),
+            vec![
+                Point::new(-2, 1),
+                Point::new(-1, 1),
+                Point::new(0, 1),
+                Point::new(1, 1)
+            ]
+        );
+        assert_eq!(
+            Line::from_string("0,1 -> 0,-2").points(),
+            vec![
+                Point::new(0, 1),
+                Point::new(0, 0),
+                Point::new(0, -1),
+                Point::new(0, -2)
+            ]
+        );
+        assert_eq!(
+            Line::from_string("-5,-7 -> -7,-5").points(),
+            vec![Point::new(-5, -7), Point::new(-6, -6), Point::new(-7, -5)]
+        );
+        for raster in [Raster::Bresenham, Raster::Lattice] {
+            assert_eq!(
+                Line::from_string_with("1,-1 -> -1,1", raster).points(),
+                vec![Point::new(1, -1), Point::new(0, 0), Point::new(-1, 1)]
+            );
+            assert_eq!(
+                Line::from_string_with("-2,-6 -> 0,0", raster)
+                    .points()
+                    .last(),
+                Some(&Point::new(0, 0))
+            );
+        }
+        // the differences don't fit into an i32
+        let extreme = Line::from_string_with("-2147483648,-2 -> 2147483646,2", Raster::Lattice);
+        assert_eq!(
+            extreme.points(),
+            vec![
+                Point::new(i32::MIN, -2),
+                Point::new(-1, 0),
+                Point::new(i32::MAX - 1, 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_solve_around_origin() {
+        // the example moved so that it crosses both axes
+        let shifted = EXAMPLE_DATA
+            .lines()
+            .map(|l| {
+                let n = l
+                    .split(" -> ")
+                    .flat_map(|p| p.split(','))
+                    .map(|n| n.parse::<i32>().unwrap() - 5)
+                    .collect::<Vec<_>>();
+                format!("{},{} -> {},{}", n[0], n[1], n[2], n[3])
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert!(shifted.starts_with("-5,4 -> 0,4\n3,-5 -> -5,3"));
+        assert_eq!(solve1(&shifted), 5);
+        assert_eq!(solve2(&shifted), 12);
+    }
+
+    #[test]
+    fn test_kinds() {
+        let kinds = [
+            "0,9 -> 5,9",
+            "7,0 -> 7,4",
+            "6,4 -> 2,0",
+            "0,0 -> 2,6",
+            "3,3 -> 3,3",
+        ]
+        .iter()
+        .map(|l| Line::from_string(l).kind())
+        .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            vec![
+                Kind::Horizontal,
+                Kind::Vertical,
+                Kind::Diagonal,
+                Kind::Other,
+                Kind::Horizontal
+            ]
+        );
+        let method = Method::Rasterize(Raster::Bresenham);
+        assert_eq!(solve_with(EXAMPLE_DATA, method, STRAIGHT), 5);
+        assert_eq!(solve_with(EXAMPLE_DATA, method, ALL), 12);
+        // the diagonals alone cross at 4,4, 5,3, 5,5 and 6,4
+        assert_eq!(solve_with(EXAMPLE_DATA, method, &[Kind::Diagonal]), 4);
+        assert_eq!(
+            solve_with(EXAMPLE_DATA, Method::Sweep, &[Kind::Diagonal]),
+            4
+        );
+    }
+
+    #[test]
+    fn test_histogram() {
+        let lines = parse_lines(EXAMPLE_DATA, Method::Rasterize(Raster::Bresenham), ALL);
+        let histogram = histogram(&lines);
+        assert_eq!(
+            histogram.iter().collect::<Vec<_>>(),
+            vec![(1, 27), (2, 10), (3, 2)]
+        );
+        assert_eq!(histogram.at_least(2), overlaps(&lines));
+        assert_eq!(histogram.at_least(3), 2);
+    }
+
     #[test]
     fn test_solve1() {
         assert_eq!(solve1(EXAMPLE_DATA), 5);
@@ -110,4 +588,23 @@ mod tests {
     fn test_solve2() {
         assert_eq!(solve2(EXAMPLE_DATA), 12);
     }
+
+    #[test]
+    fn test_solve2_lattice() {
+        let lattice = Method::Rasterize(Raster::Lattice);
+        assert_eq!(solve2_with(EXAMPLE_DATA, lattice), 12);
+        // the steep line crosses the horizontal one between lattice points
+        let input = "0,0 -> 2,6\n0,2 -> 4,2";
+        assert_eq!(solve2_with(input, Method::Rasterize(Raster::Bresenham)), 1);
+        assert_eq!(solve2_with(input, lattice), 0);
+    }
+
+    #[test]
+    fn test_solve_sweep() {
+        assert_eq!(solve1_with(EXAMPLE_DATA, Method::Sweep), 5);
+        assert_eq!(solve2_with(EXAMPLE_DATA, Method::Sweep), 12);
+        // far too many points to rasterize
+        let input = "0,0 -> 2000000000,0\n1000000000,0 -> 1500000000,0\n5,-5 -> 5,5";
+        assert_eq!(solve1_with(input, Method::Sweep), 500000002);
+    }
 }
@@ -0,0 +1,210 @@
+use crate::{
+    counter::{bounding_box, tally, DenseCounter, SparseCounter, DENSE_LIMIT},
+    Line,
+};
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+/// Widest map, in cells, that is still drawn as text.
+pub const ASCII_LIMIT: usize = 200;
+
+/// How often every cell in the bounding box of the lines is covered, ready to be drawn. Large
+/// maps are scaled down, a cell then shows the highest count of the points it covers.
+pub struct VentMap {
+    /// The counts row by row, from the smallest `y` up.
+    cells: Vec<u16>,
+    width: usize,
+    height: usize,
+    scale: u64,
+}
+
+impl VentMap {
+    /// A map of at most `DENSE_LIMIT` cells, `None` if there are no lines.
+    pub fn new(lines: &[Line]) -> Option<Self> {
+        Self::with_limit(lines, DENSE_LIMIT)
+    }
+    /// A map of at most `limit` cells, `None` if there are no lines.
+    pub fn with_limit(lines: &[Line], limit: u64) -> Option<Self> {
+        let (min, max) = bounding_box(lines)?;
+        let width = (max.x as i64 - min.x as i64 + 1) as u64;
+        let height = (max.y as i64 - min.y as i64 + 1) as u64;
+        // start a little below the square root of the ratio and step up to the first fit
+        let ratio = width as f64 * height as f64 / limit.max(1) as f64;
+        let mut scale = (ratio.sqrt() as u64).max(1);
+        while width.div_ceil(scale).saturating_mul(height.div_ceil(scale)) > limit.max(1) {
+            scale += 1;
+        }
+        let (w, h) = (width.div_ceil(scale), height.div_ceil(scale));
+        let cells = if scale == 1 {
+            let mut grid = DenseCounter::new(min, max);
+            tally(&mut grid, lines);
+            grid.rows().flatten().copied().collect()
+        } else {
+            let mut points = SparseCounter::default();
+            tally(&mut points, lines);
+            let mut cells = vec![0_u16; (w * h) as usize];
+            for (point, count) in points.iter() {
+                let x = (point.x as i64 - min.x as i64) as u64 / scale;
+                let y = (point.y as i64 - min.y as i64) as u64 / scale;
+                let cell = &mut cells[(y * w + x) as usize];
+                *cell = (*cell).max(count.min(u16::MAX as u32) as u16);
+            }
+            cells
+        };
+        Some(Self {
+            cells,
+            width: w as usize,
+            height: h as usize,
+            scale,
+        })
+    }
+    pub fn width(&self) -> usize {
+        self.width
+    }
+    pub fn height(&self) -> usize {
+        self.height
+    }
+    /// Points per cell in each direction.
+    pub fn scale(&self) -> u64 {
+        self.scale
+    }
+    fn rows(&self) -> impl Iterator<Item = &[u16]> {
+        self.cells.chunks(self.width)
+    }
+    /// The diagram from the puzzle text, `.` for cells covered less than `threshold` times
+    /// and the count otherwise. Counts above 9 are drawn as `#`.
+    pub fn to_ascii(&self, threshold: u16) -> String {
+        let mut out = String::with_capacity((self.width + 1) * self.height);
+        for row in self.rows() {
+            for &count in row {
+                out.push(match count {
+                    0 => '.',
+                    n if n < threshold => '.',
+                    n if n <= 9 => (b'0' + n as u8) as char,
+                    _ => '#',
+                });
+            }
+            out.push('\n');
+        }
+        out
+    }
+    /// A gray value per cell, white for the highest count. Cells covered less than
+    /// `threshold` times stay black.
+    fn shades(&self, threshold: u16) -> Vec<u8> {
+        let max = self.cells.iter().copied().max().unwrap_or(0).max(1) as u32;
+        self.cells
+            .iter()
+            .map(|&count| match count {
+                n if n < threshold => 0,
+                n => (n as u32 * 255 / max) as u8,
+            })
+            .collect()
+    }
+    /// Writes a binary PGM heatmap.
+    pub fn write_pgm(&self, out: &mut impl Write, threshold: u16) -> io::Result<()> {
+        write!(out, "P5\n{} {}\n255\n", self.width, self.height)?;
+        out.write_all(&self.shades(threshold))
+    }
+    /// Writes a grayscale PNG heatmap.
+    pub fn write_png(&self, out: impl Write, threshold: u16) -> io::Result<()> {
+        let mut encoder = png::Encoder::new(out, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.shades(threshold))?;
+        Ok(())
+    }
+    /// Writes a heatmap, as PNG if the file name ends in `.png` and as PGM otherwise.
+    pub fn save(&self, path: &Path, threshold: u16) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("png") => self.write_png(out, threshold),
+            _ => self.write_pgm(&mut out, threshold),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{lines, EXAMPLE_DATA};
+
+    #[test]
+    fn test_to_ascii() {
+        let example = lines(EXAMPLE_DATA);
+        let map = VentMap::new(&example).unwrap();
+        assert_eq!((map.width(), map.height()), (10, 10));
+        // the diagram from the puzzle text
+        assert_eq!(
+            map.to_ascii(1),
+            "1.1....11.
+.111...2..
+..2.1.111.
+...1.2.2..
+.112313211
+...1.2....
+..1...1...
+.1.....1..
+1.......1.
+222111....
+"
+        );
+        assert_eq!(
+            map.to_ascii(2).lines().take(5).collect::<Vec<_>>(),
+            vec![
+                "..........",
+                ".......2..",
+                "..2.......",
+                ".....2.2..",
+                "...23.32.."
+            ]
+        );
+        let crowded = lines(&"-1,0 -> 1,0\n".repeat(12));
+        assert_eq!(VentMap::new(&crowded).unwrap().to_ascii(1), "###\n");
+    }
+
+    #[test]
+    fn test_heatmaps() {
+        let map = VentMap::new(&lines("0,0 -> 2,0\n1,0 -> 1,1\n1,1 -> 1,1")).unwrap();
+        let mut pgm = Vec::new();
+        map.write_pgm(&mut pgm, 1).unwrap();
+        assert_eq!(pgm, b"P5\n3 2\n255\n\x7f\xff\x7f\x00\xff\x00".to_vec());
+        assert_eq!(map.shades(2), vec![0, 255, 0, 0, 255, 0]);
+
+        let mut png = Vec::new();
+        map.write_png(&mut png, 1).unwrap();
+        let decoder = png::Decoder::new(png.as_slice());
+        let mut reader = decoder.read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+        assert_eq!((info.width, info.height), (3, 2));
+        assert_eq!(pixels, map.shades(1));
+    }
+
+    #[test]
+    fn test_scaled_down() {
+        let example = VentMap::with_limit(&lines(EXAMPLE_DATA), 25).unwrap();
+        assert_eq!(
+            (example.width(), example.height(), example.scale()),
+            (5, 5, 2)
+        );
+        // the highest count of every two by two block
+        assert_eq!(
+            example.to_ascii(1),
+            "11.21
+.2221
+12331
+11.1.
+221.1
+"
+        );
+        let large = VentMap::new(&lines("0,0 -> 100000,100000\n0,100000 -> 100000,0")).unwrap();
+        assert!((large.width() * large.height()) as u64 <= DENSE_LIMIT);
+        assert_eq!(large.scale(), 25);
+        assert_eq!(large.cells.iter().filter(|&&n| n == 2).count(), 1);
+        assert!(VentMap::new(&[]).is_none());
+    }
+}
@@ -0,0 +1,237 @@
+use crate::{Line, Point};
+use std::collections::HashMap;
+
+/// All segments lying on the same infinite line, as intervals of the step index along it.
+///
+/// A line through lattice points with the primitive direction `(dx, dy)` visits one lattice
+/// point per step. The step index of a point is `x / dx`, or `y` for vertical lines, which is
+/// the same for every segment on the line.
+struct Group {
+    direction: (i64, i64),
+    origin: (i64, i64),
+    /// Runs of covered step indices as `(from, to, coverage)`, sorted and not overlapping.
+    runs: Vec<(i64, i64, usize)>,
+}
+
+impl Group {
+    fn step(&self, x: i64, y: i64) -> i64 {
+        step(self.direction, x, y)
+    }
+    fn coverage(&self, k: i64) -> usize {
+        let i = self.runs.partition_point(|&(_, to, _)| to < k);
+        match self.runs.get(i) {
+            Some(&(from, _, coverage)) if from <= k => coverage,
+            _ => 0,
+        }
+    }
+    /// Points covered by at least two segments of this line.
+    fn overlaps(&self) -> usize {
+        self.runs
+            .iter()
+            .filter(|&&(_, _, coverage)| coverage >= 2)
+            .map(|&(from, to, _)| (to - from + 1) as usize)
+            .sum()
+    }
+}
+
+/// A line through lattice points, as its primitive direction and the cross product of the
+/// direction with any point on it.
+type LineKey = ((i64, i64), i128);
+
+fn step(direction: (i64, i64), x: i64, y: i64) -> i64 {
+    if direction.0 != 0 {
+        x.div_euclid(direction.0)
+    } else {
+        y
+    }
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// Primitive direction of a segment, pointing right or, for vertical lines, up. Single
+/// points count as horizontal.
+fn direction(line: &Line) -> (i64, i64) {
+    let dx = line.end.x as i64 - line.start.x as i64;
+    let dy = line.end.y as i64 - line.start.y as i64;
+    let g = gcd(dx.abs(), dy.abs());
+    if g == 0 {
+        return (1, 0);
+    }
+    let (dx, dy) = (dx / g, dy / g);
+    if dx < 0 || (dx == 0 && dy < 0) {
+        (-dx, -dy)
+    } else {
+        (dx, dy)
+    }
+}
+
+/// Merges the step intervals of one line into runs of equal coverage.
+fn runs(intervals: &[(i64, i64)]) -> Vec<(i64, i64, usize)> {
+    let mut events = intervals
+        .iter()
+        .flat_map(|&(from, to)| [(from, 1_i64), (to + 1, -1)])
+        .collect::<Vec<_>>();
+    events.sort_unstable();
+    let mut runs = Vec::new();
+    let mut coverage = 0_i64;
+    let mut i = 0;
+    while i < events.len() {
+        let at = events[i].0;
+        while i < events.len() && events[i].0 == at {
+            coverage += events[i].1;
+            i += 1;
+        }
+        if coverage > 0 {
+            if let Some(&(next, _)) = events.get(i) {
+                runs.push((at, next - 1, coverage as usize));
+            }
+        }
+    }
+    runs
+}
+
+/// Counts the points covered by at least two lines from their end points alone.
+///
+/// Segments are grouped by the infinite line they lie on, overlaps within a group are
+/// found by sweeping over their intervals, and every pair of non-parallel groups can add one
+/// more point where they cross. Only lattice points count, which matches the rasterized
+/// count for horizontal, vertical and 45 degree lines, and `Raster::Lattice` for any line.
+pub fn overlaps(lines: &[Line]) -> usize {
+    let mut grouped: HashMap<LineKey, (Point, Vec<(i64, i64)>)> = HashMap::new();
+    for line in lines {
+        let d = direction(line);
+        let (x0, y0) = (line.start.x as i64, line.start.y as i64);
+        let (x1, y1) = (line.end.x as i64, line.end.y as i64);
+        let key = (d, d.1 as i128 * x0 as i128 - d.0 as i128 * y0 as i128);
+        let (k0, k1) = (step(d, x0, y0), step(d, x1, y1));
+        grouped
+            .entry(key)
+            .or_insert_with(|| (line.start, Vec::new()))
+            .1
+            .push((k0.min(k1), k0.max(k1)));
+    }
+    let groups = grouped
+        .into_iter()
+        .map(|((direction, _), (origin, intervals))| Group {
+            direction,
+            origin: (origin.x as i64, origin.y as i64),
+            runs: runs(&intervals),
+        })
+        .collect::<Vec<_>>();
+
+    let mut total: usize = groups.iter().map(|g| g.overlaps()).sum();
+    // crossings, together with how many groups already counted them as an overlap
+    let mut crossings: HashMap<(i64, i64), usize> = HashMap::new();
+    for (i, a) in groups.iter().enumerate() {
+        for b in groups[i + 1..].iter() {
+            if let Some(p) = crossing(a, b) {
+                crossings.entry(p).or_insert_with(|| {
+                    groups
+                        .iter()
+                        .filter(|g| g.coverage(g.step(p.0, p.1)) >= 2 && on_line(g, p))
+                        .count()
+                });
+            }
+        }
+    }
+    for counted in crossings.values() {
+        total = total + 1 - counted;
+    }
+    total
+}
+
+fn on_line(group: &Group, p: (i64, i64)) -> bool {
+    let (dx, dy) = (group.direction.0 as i128, group.direction.1 as i128);
+    dy * (p.0 as i128 - group.origin.0 as i128) == dx * (p.1 as i128 - group.origin.1 as i128)
+}
+
+/// The lattice point where the covered parts of two groups cross, if any.
+fn crossing(a: &Group, b: &Group) -> Option<(i64, i64)> {
+    let (ax, ay) = (a.direction.0 as i128, a.direction.1 as i128);
+    let (bx, by) = (b.direction.0 as i128, b.direction.1 as i128);
+    let den = ax * by - ay * bx;
+    if den == 0 {
+        return None;
+    }
+    let wx = (b.origin.0 - a.origin.0) as i128;
+    let wy = (b.origin.1 - a.origin.1) as i128;
+    let num = wx * by - wy * bx;
+    if num % den != 0 {
+        return None;
+    }
+    let s = num / den;
+    let x = i64::try_from(a.origin.0 as i128 + s * ax).ok()?;
+    let y = i64::try_from(a.origin.1 as i128 + s * ay).ok()?;
+    [a, b]
+        .iter()
+        .all(|g| g.coverage(g.step(x, y)) > 0)
+        .then_some((x, y))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        fixtures::{lines_with, random},
+        Raster,
+    };
+
+    #[test]
+    fn test_runs() {
+        assert_eq!(
+            runs(&[(0, 5), (3, 8), (4, 4), (10, 10)]),
+            vec![
+                (0, 2, 1),
+                (3, 3, 2),
+                (4, 4, 3),
+                (5, 5, 2),
+                (6, 8, 1),
+                (10, 10, 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_overlaps() {
+        // collinear diagonals overlapping in two points, crossed by a vertical line
+        let input = "0,0 -> 4,4\n3,3 -> 6,6\n3,0 -> 3,9\n-1,2 -> 5,2\n0,6 -> 6,0";
+        let crossed = lines_with(input, Raster::Bresenham);
+        assert_eq!(overlaps(&crossed), crate::overlaps(&crossed));
+        assert_eq!(overlaps(&crossed), 5);
+        // a point lying on another line
+        let points = lines_with("2,2 -> 2,2\n0,0 -> 4,4\n7,7 -> 7,7", Raster::Lattice);
+        assert_eq!(overlaps(&points), 1);
+    }
+
+    #[test]
+    fn test_overlaps_matches_rasterizing() {
+        // pseudo random lines of any slope, including repeated and collinear ones
+        let mut next = random(7);
+        for _ in 0..20 {
+            let input = (0..40)
+                .map(|_| {
+                    let (x, y) = (next(30), next(30));
+                    let (dx, dy) = match next(4) {
+                        -2 => (next(30), 0),
+                        -1 => (0, next(30)),
+                        0 => {
+                            let n = next(30);
+                            (n, n * next(2).signum())
+                        }
+                        _ => (next(12), next(12)),
+                    };
+                    format!("{},{} -> {},{}", x, y, x + dx, y + dy)
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            let lines = lines_with(&input, Raster::Lattice);
+            assert_eq!(overlaps(&lines), crate::overlaps(&lines), "{}", input);
+        }
+    }
+}
@@ -0,0 +1,208 @@
+/// When lanternfish spawn and die. The default is the puzzle's: timers reset to 6, newborn
+/// fish start at 8 and fish spawn and live forever.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lifecycle {
+    /// Timer of a fish right after it spawned.
+    pub reset: u8,
+    /// Timer of a newborn fish.
+    pub newborn: u8,
+    /// Days a fish has to live before it spawns, younger fish only reset their timer.
+    pub maturity: Option<u16>,
+    /// Fish die once they are this many days old.
+    pub max_age: Option<u16>,
+}
+
+impl Default for Lifecycle {
+    fn default() -> Self {
+        Self {
+            reset: 6,
+            newborn: 8,
+            maturity: None,
+            max_age: None,
+        }
+    }
+}
+
+impl Lifecycle {
+    /// Age of the fish in the input, they count as just mature.
+    pub fn initial_age(&self) -> u16 {
+        self.maturity.unwrap_or(0)
+    }
+    /// Fish only need to know their age for maturity or mortality.
+    pub fn counts_age(&self) -> bool {
+        self.maturity.is_some() || self.max_age.is_some()
+    }
+    pub fn is_mature(&self, age: usize) -> bool {
+        self.maturity.is_none_or(|m| age >= m as usize)
+    }
+    pub fn is_alive(&self, age: usize) -> bool {
+        self.max_age.is_none_or(|m| age < m as usize)
+    }
+}
+
+/// Groups fish by age and timer, so that all fish in a group behave the same. Ages are only
+/// told apart as far as maturity and mortality need them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layout {
+    lifecycle: Lifecycle,
+    ages: usize,
+    timers: usize,
+}
+
+impl Layout {
+    pub fn new(lifecycle: Lifecycle, input: &[u8]) -> Self {
+        let ages = match (lifecycle.max_age, lifecycle.maturity) {
+            (Some(max_age), _) => max_age as usize,
+            (None, Some(maturity)) => maturity as usize + 1,
+            (None, None) => 1,
+        };
+        let timers = input
+            .iter()
+            .copied()
+            .chain([lifecycle.reset, lifecycle.newborn])
+            .max()
+            .unwrap() as usize
+            + 1;
+        Self {
+            lifecycle,
+            ages,
+            timers,
+        }
+    }
+    /// Number of groups.
+    pub fn len(&self) -> usize {
+        self.ages * self.timers
+    }
+    /// Number of different timers.
+    pub fn timers(&self) -> usize {
+        self.timers
+    }
+    /// The timer of the fish in a group.
+    pub fn timer(&self, group: usize) -> usize {
+        group % self.timers
+    }
+    fn index(&self, age: usize, timer: u8) -> Option<usize> {
+        if !self.lifecycle.is_alive(age) {
+            return None;
+        }
+        let age = age.min(self.ages - 1);
+        Some(age * self.timers + timer as usize)
+    }
+    /// Number of fish in every group on the first day.
+    pub fn initial(&self, input: &[u8]) -> Vec<u64> {
+        let mut groups = vec![0; self.len()];
+        for &timer in input {
+            if let Some(i) = self.index(self.lifecycle.initial_age() as usize, timer) {
+                groups[i] += 1;
+            }
+        }
+        groups
+    }
+    /// Every `(from, to)` where one fish in group `from` turns into one fish in group `to` the
+    /// next day, as `Fish::next_day_with` does for a single fish.
+    pub fn transitions(&self) -> Vec<(usize, usize)> {
+        let mut transitions = Vec::new();
+        for age in 0..self.ages {
+            for timer in 0..self.timers as u8 {
+                let from = match self.index(age, timer) {
+                    Some(from) => from,
+                    None => continue,
+                };
+                let next = if timer == 0 {
+                    self.lifecycle.reset
+                } else {
+                    timer - 1
+                };
+                if let Some(to) = self.index(age + 1, next) {
+                    transitions.push((from, to));
+                }
+                if timer == 0 && self.lifecycle.is_mature(age) {
+                    if let Some(to) = self.index(0, self.lifecycle.newborn) {
+                        transitions.push((from, to));
+                    }
+                }
+            }
+        }
+        transitions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{solve1_with, solve2_with};
+
+    #[test]
+    fn test_default_layout() {
+        let layout = Layout::new(Lifecycle::default(), &[3, 4, 3, 1, 2]);
+        assert_eq!(layout.len(), 9);
+        assert_eq!(
+            layout.initial(&[3, 4, 3, 1, 2]),
+            vec![0, 1, 1, 2, 1, 0, 0, 0, 0]
+        );
+        let mut transitions = layout.transitions();
+        transitions.sort_unstable();
+        assert_eq!(
+            transitions,
+            vec![
+                (0, 6),
+                (0, 8),
+                (1, 0),
+                (2, 1),
+                (3, 2),
+                (4, 3),
+                (5, 4),
+                (6, 5),
+                (7, 6),
+                (8, 7)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_layout_with_ages() {
+        let lifecycle = Lifecycle {
+            reset: 2,
+            newborn: 3,
+            maturity: Some(5),
+            max_age: Some(10),
+        };
+        let layout = Layout::new(lifecycle, &[1, 4]);
+        // ages 0 to 9 and timers 0 to 4
+        assert_eq!(layout.len(), 50);
+        let group = |age: usize, timer: usize| age * 5 + timer;
+        let initial = layout.initial(&[1, 4]);
+        assert_eq!(initial[group(5, 1)], 1);
+        assert_eq!(initial[group(5, 4)], 1);
+        let transitions = layout.transitions();
+        // a young fish only resets, a mature one also spawns, the oldest ones die
+        assert!(transitions.contains(&(group(1, 0), group(2, 2))));
+        assert!(!transitions.contains(&(group(1, 0), group(0, 3))));
+        assert!(transitions.contains(&(group(5, 0), group(6, 2))));
+        assert!(transitions.contains(&(group(5, 0), group(0, 3))));
+        assert!(!transitions
+            .iter()
+            .any(|&(from, to)| from >= group(9, 0) && to >= group(1, 0)));
+    }
+
+    #[test]
+    fn test_oldest_ages() {
+        for (maturity, max_age) in [(Some(u16::MAX), None), (None, Some(u16::MAX))] {
+            let lifecycle = Lifecycle {
+                maturity,
+                max_age,
+                ..Lifecycle::default()
+            };
+            assert_eq!(
+                Layout::new(lifecycle, &[3]).len(),
+                65536 * 9 - 9 * max_age.is_some() as usize
+            );
+            for days in [5, 20] {
+                assert_eq!(
+                    solve2_with(&[3], days, lifecycle),
+                    solve1_with(&[3], days, lifecycle) as u128
+                );
+            }
+        }
+    }
+}
@@ -1,50 +1,87 @@
-use std::{collections::VecDeque, fs};
+use lifecycle::Lifecycle;
+use precision::{Overflow, OverflowError};
+use series::Population;
+use std::fs;
+use stochastic::{Distribution, StochasticOptions};
+
+mod lifecycle;
+mod matrix;
+mod precision;
+mod series;
+mod stochastic;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Fish {
     timer: u8,
+    /// Days lived, only counted by lifecycles with maturity or mortality.
+    age: u16,
 }
 
 impl Fish {
     pub fn new(timer: u8) -> Self {
-        Self { timer }
+        Self { timer, age: 0 }
     }
     pub fn spawn() -> Self {
-        Self { timer: 8 }
+        Self::spawn_with(&Lifecycle::default())
+    }
+    pub fn spawn_with(lifecycle: &Lifecycle) -> Self {
+        Self {
+            timer: lifecycle.newborn,
+            age: 0,
+        }
     }
     pub fn next_day(&mut self) -> Option<Fish> {
+        self.next_day_with(&Lifecycle::default())
+    }
+    /// Ages the fish by a day and returns the fish it spawned. A fish may spawn on the day it
+    /// dies.
+    pub fn next_day_with(&mut self, lifecycle: &Lifecycle) -> Option<Fish> {
+        let mature = lifecycle.is_mature(self.age as usize);
+        if lifecycle.counts_age() {
+            self.age = self.age.saturating_add(1);
+        }
         if self.timer == 0 {
-            self.timer = 6;
-            Some(Self::spawn())
+            self.timer = lifecycle.reset;
+            mature.then(|| Self::spawn_with(lifecycle))
         } else {
             self.timer -= 1;
             None
         }
     }
 }
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct School {
     fish: Vec<Fish>,
+    lifecycle: Lifecycle,
 }
 
 impl School {
     pub fn new(timers: &[u8]) -> Self {
-        let fish = timers.iter().map(|&n| Fish::new(n)).collect();
-        Self { fish }
+        Self::new_with(timers, Lifecycle::default())
+    }
+    pub fn new_with(timers: &[u8], lifecycle: Lifecycle) -> Self {
+        let fish = timers
+            .iter()
+            .map(|&n| Fish {
+                timer: n,
+                age: lifecycle.initial_age(),
+            })
+            .filter(|f| lifecycle.is_alive(f.age as usize))
+            .collect();
+        Self { fish, lifecycle }
     }
     pub fn add(&mut self, fish: Fish) {
         self.fish.push(fish)
     }
-    pub fn default() -> Self {
-        Self { fish: Vec::new() }
-    }
     pub fn next_day(&mut self) {
         let mut spawned = Vec::new();
         for f in self.fish.iter_mut() {
-            if let Some(f) = f.next_day() {
+            if let Some(f) = f.next_day_with(&self.lifecycle) {
                 spawned.push(f);
             }
         }
+        let lifecycle = self.lifecycle;
+        self.fish.retain(|f| lifecycle.is_alive(f.age as usize));
         self.fish.append(&mut spawned);
     }
     pub fn count(&self) -> usize {
@@ -58,14 +95,141 @@ fn main() {
         .split(',')
         .map(|n| n.parse::<u8>().unwrap())
         .collect::<Vec<_>>();
-    let part1 = solve1(&input, 80);
+    let mut lifecycle = Lifecycle::default();
+    let mut days = None;
+    let mut modulus = None;
+    let mut overflow = Overflow::BigInt;
+    let mut series: Option<usize> = None;
+    let mut json = false;
+    let mut threshold = None;
+    let mut trials = None;
+    let mut spawns = Distribution::Fixed(1);
+    let mut gestation = None;
+    let mut survival = 1.0;
+    let mut seed = 0;
+    let mut threads = 1;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().expect("missing argument value");
+        match arg.as_str() {
+            // any number of days, exact or modulo a prime
+            "--days" => days = Some(value().parse().unwrap()),
+            "--modulo" => modulus = Some(value().parse().unwrap()),
+            // stop once the count doesn't fit into a u128 instead of counting on exactly
+            "--overflow" => {
+                overflow = match value().as_str() {
+                    "error" => Overflow::Error,
+                    "bigint" => Overflow::BigInt,
+                    other => panic!("Unknown overflow handling {}", other),
+                }
+            }
+            "--reset" => lifecycle.reset = value().parse().unwrap(),
+            "--newborn" => lifecycle.newborn = value().parse().unwrap(),
+            "--maturity" => lifecycle.maturity = Some(value().parse().unwrap()),
+            "--max-age" => lifecycle.max_age = Some(value().parse().unwrap()),
+            // print the population of every day up to the given one, as CSV or JSON
+            "--series" => series = Some(value().parse().unwrap()),
+            "--json" => json = true,
+            // the first day with more fish than this
+            "--above" => threshold = Some(value().parse().unwrap()),
+            // random spawns, gestation and deaths, distributions are given like 6, 5-7 or
+            // 0:0.2,1:0.8
+            "--stochastic" => trials = Some(value().parse().unwrap()),
+            "--spawns" => spawns = Distribution::from_string(&value()),
+            "--gestation" => gestation = Some(Distribution::from_string(&value())),
+            "--survival" => survival = value().parse().unwrap(),
+            "--seed" => seed = value().parse().unwrap(),
+            "--threads" => threads = value().parse().unwrap(),
+            _ => panic!("Unknown argument {}", arg),
+        }
+    }
+    if let Some(days) = series {
+        let days = match series::series(&input, lifecycle, days, overflow) {
+            Ok(days) => days,
+            Err(error) => {
+                eprintln!("{}", error);
+                std::process::exit(1);
+            }
+        };
+        if json {
+            println!("{}", series::to_json(&days));
+        } else {
+            print!("{}", series::to_csv(&days));
+        }
+        return;
+    }
+    if let Some(trials) = trials {
+        let model = stochastic::Model {
+            spawns,
+            gestation: gestation.unwrap_or(Distribution::Fixed(lifecycle.reset as u32)),
+            survival,
+            ..stochastic::Model::from_lifecycle(lifecycle)
+        };
+        let options = StochasticOptions {
+            trials,
+            days: days.unwrap_or(256) as usize,
+            seed,
+            threads,
+            overflow,
+        };
+        let days = match stochastic::simulate(&input, &model, options, &[5.0, 50.0, 95.0]) {
+            Ok(days) => days,
+            Err(error) => {
+                eprintln!("{}", error);
+                std::process::exit(1);
+            }
+        };
+        println!("day           mean        p5       p50       p95");
+        for stats in days {
+            let p = &stats.percentiles;
+            println!(
+                "{:>3} {:>14.1} {:>9} {:>9} {:>9}",
+                stats.day, stats.mean, p[0], p[1], p[2]
+            );
+        }
+        return;
+    }
+    let part1 = solve1_with(&input, 80, lifecycle);
     println!("Part 1: {}", &part1);
-    let part2 = solve2(&input, 256);
+    let part2 = solve2_with(&input, 256, lifecycle);
     println!("Part 2: {}", &part2);
+    if let Some(days) = days {
+        match modulus {
+            Some(modulus) => println!(
+                "After {} days: {} (mod {})",
+                days,
+                matrix::count_modulo(&input, days, modulus, lifecycle),
+                modulus
+            ),
+            None if overflow == Overflow::Error => {
+                match precision::count(&input, days, lifecycle, overflow) {
+                    Ok(count) => println!("After {} days: {}", days, count),
+                    Err(error) => {
+                        eprintln!("{}", error);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            None => println!(
+                "After {} days: {}",
+                days,
+                matrix::count_exact(&input, days, lifecycle)
+            ),
+        }
+    }
+    if let Some(threshold) = threshold {
+        match series::first_day_above(&input, lifecycle, threshold, 10_000) {
+            Some(day) => println!("More than {} fish on day {}", threshold, day),
+            None => println!("Never more than {} fish in 10000 days", threshold),
+        }
+    }
 }
 pub fn solve1(input: &[u8], days: u16) -> usize {
+    solve1_with(input, days, Lifecycle::default())
+}
+pub fn solve1_with(input: &[u8], days: u16, lifecycle: Lifecycle) -> usize {
     // This is brute force:
-    let mut school = School::new(input);
+    let mut school = School::new_with(input, lifecycle);
     for _ in 0..days {
         school.next_day();
     }
@@ -73,25 +237,26 @@ pub fn solve1(input: &[u8], days: u16) -> usize {
 }
 
 pub fn solve2(input: &[u8], days: u16) -> u128 {
-    // count birthdays
-    let mut buckets: VecDeque<u128> = [0_u128; 9].into_iter().collect();
-    for n in input {
-        buckets[*n as usize] += 1;
-    }
+    solve2_with(input, days, Lifecycle::default())
+}
+pub fn solve2_with(input: &[u8], days: u16, lifecycle: Lifecycle) -> u128 {
+    // count fish per age and timer
+    let mut population = Population::new(input, lifecycle);
     for _ in 0..days {
-        if let Some(s) = buckets.pop_front() {
-            buckets.push_back(s);
-            buckets[6] += s;
-        } else {
-            panic!("empty queue!");
-        }
+        population.try_advance().unwrap_or_else(|e| panic!("{}", e));
     }
-    buckets.iter().sum()
+    population
+        .total()
+        .unwrap_or_else(|| panic!("{}", OverflowError { day: days as u64 }))
+}
+#[cfg(test)]
+mod fixtures {
+    pub static EXAMPLE_DATA: [u8; 5] = [3, 4, 3, 1, 2];
 }
 #[cfg(test)]
 mod tests {
     use super::*;
-    static EXAMPLE_DATA: [u8; 5] = [3, 4, 3, 1, 2];
+    use crate::fixtures::EXAMPLE_DATA;
     #[test]
     fn test_fish_next_day() {
         let mut fish = Fish::new(0);
@@ -99,7 +264,7 @@ mod tests {
         let spawned = fish.next_day();
         let spawned2 = fish2.next_day();
         assert_eq!(fish.timer, 6);
-        assert_eq!(spawned, Some(Fish { timer: 8 }));
+        assert_eq!(spawned, Some(Fish { timer: 8, age: 0 }));
         assert_eq!(fish2.timer, 0);
         assert_eq!(spawned2, None);
     }
@@ -125,6 +290,61 @@ mod tests {
         assert_eq!(school.count(), 6);
     }
 
+    #[test]
+    fn test_lifecycle() {
+        let default = Lifecycle::default();
+        assert_eq!(solve1_with(&EXAMPLE_DATA, 80, default), 5934);
+        assert_eq!(solve2_with(&EXAMPLE_DATA, 80, default), 5934);
+
+        // every fish spawns every third day and newborns wait a day longer
+        let fast = Lifecycle {
+            reset: 2,
+            newborn: 3,
+            ..default
+        };
+        let mut school = School::new_with(&[0], fast);
+        school.next_day();
+        assert_eq!(school.fish, vec![Fish::new(2), Fish::new(3)]);
+        for days in [0, 1, 10, 40] {
+            assert_eq!(
+                solve1_with(&EXAMPLE_DATA, days, fast) as u128,
+                solve2_with(&EXAMPLE_DATA, days, fast)
+            );
+        }
+
+        // fish die at 20 days and spawn from 10 days on
+        let mortal = Lifecycle {
+            maturity: Some(10),
+            max_age: Some(20),
+            ..default
+        };
+        let mut fish = Fish::spawn_with(&mortal);
+        for _ in 0..9 {
+            fish.next_day_with(&mortal);
+        }
+        assert_eq!(fish.timer, 6);
+        for days in [0, 5, 10, 19, 30, 100] {
+            assert_eq!(
+                solve1_with(&EXAMPLE_DATA, days, mortal) as u128,
+                solve2_with(&EXAMPLE_DATA, days, mortal)
+            );
+        }
+        // the input fish start out at maturity, spawn twice and die after ten days
+        assert_eq!(solve2_with(&[0], 9, mortal), 3);
+        assert_eq!(solve2_with(&[0], 10, mortal), 2);
+        assert_eq!(solve2_with(&EXAMPLE_DATA, 10, mortal), 5 + 2);
+        let short = Lifecycle {
+            reset: 2,
+            newborn: 3,
+            maturity: Some(3),
+            max_age: Some(8),
+        };
+        assert_eq!(
+            matrix::count_exact(&EXAMPLE_DATA, 100, short),
+            solve2_with(&EXAMPLE_DATA, 100, short).into()
+        );
+    }
+
     #[test]
     fn test_solve1() {
         assert_eq!(solve1(&EXAMPLE_DATA, 18), 26);
@@ -0,0 +1,223 @@
+use crate::lifecycle::{Layout, Lifecycle};
+use num_bigint::BigUint;
+
+/// The number type used for the counts, so that the same matrix power gives exact counts and
+/// counts modulo some number.
+pub trait Arithmetic {
+    type Value: Clone;
+    fn number(&self, n: u64) -> Self::Value;
+    fn add(&self, a: &Self::Value, b: &Self::Value) -> Self::Value;
+    fn mul(&self, a: &Self::Value, b: &Self::Value) -> Self::Value;
+}
+
+/// Exact counts of any size.
+pub struct Exact;
+
+impl Arithmetic for Exact {
+    type Value = BigUint;
+    fn number(&self, n: u64) -> BigUint {
+        BigUint::from(n)
+    }
+    fn add(&self, a: &BigUint, b: &BigUint) -> BigUint {
+        a + b
+    }
+    fn mul(&self, a: &BigUint, b: &BigUint) -> BigUint {
+        a * b
+    }
+}
+
+/// Counts modulo a number, usually a large prime.
+pub struct Modulo(pub u64);
+
+impl Arithmetic for Modulo {
+    type Value = u64;
+    fn number(&self, n: u64) -> u64 {
+        n % self.0
+    }
+    fn add(&self, a: &u64, b: &u64) -> u64 {
+        ((*a as u128 + *b as u128) % self.0 as u128) as u64
+    }
+    fn mul(&self, a: &u64, b: &u64) -> u64 {
+        ((*a as u128 * *b as u128) % self.0 as u128) as u64
+    }
+}
+
+/// A square matrix, `rows[i][j]` is how many fish in group `i` of a `Layout` one fish in
+/// group `j` turns into after one day.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Matrix<T> {
+    rows: Vec<Vec<T>>,
+}
+
+impl<T: Clone> Matrix<T> {
+    pub fn identity<A: Arithmetic<Value = T>>(arithmetic: &A, size: usize) -> Self {
+        let rows = (0..size)
+            .map(|i| {
+                (0..size)
+                    .map(|j| arithmetic.number((i == j) as u64))
+                    .collect()
+            })
+            .collect();
+        Self { rows }
+    }
+    /// One day for the fish grouped by `layout`.
+    pub fn transition<A: Arithmetic<Value = T>>(arithmetic: &A, layout: &Layout) -> Self {
+        let mut counts = vec![vec![0; layout.len()]; layout.len()];
+        for (from, to) in layout.transitions() {
+            counts[to][from] += 1;
+        }
+        let rows = counts
+            .into_iter()
+            .map(|row| row.into_iter().map(|n| arithmetic.number(n)).collect())
+            .collect();
+        Self { rows }
+    }
+    pub fn multiply<A: Arithmetic<Value = T>>(&self, arithmetic: &A, other: &Self) -> Self {
+        let size = self.rows.len();
+        let rows = (0..size)
+            .map(|i| {
+                (0..size)
+                    .map(|j| {
+                        (0..size).fold(arithmetic.number(0), |sum, k| {
+                            arithmetic
+                                .add(&sum, &arithmetic.mul(&self.rows[i][k], &other.rows[k][j]))
+                        })
+                    })
+                    .collect()
+            })
+            .collect();
+        Self { rows }
+    }
+    /// The matrix multiplied with itself `exponent` times, by repeated squaring.
+    pub fn power<A: Arithmetic<Value = T>>(&self, arithmetic: &A, mut exponent: u64) -> Self {
+        let mut result = Self::identity(arithmetic, self.rows.len());
+        let mut square = self.clone();
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = result.multiply(arithmetic, &square);
+            }
+            exponent >>= 1;
+            if exponent > 0 {
+                square = square.multiply(arithmetic, &square);
+            }
+        }
+        result
+    }
+    /// Applies the matrix to the number of fish per timer.
+    pub fn apply<A: Arithmetic<Value = T>>(&self, arithmetic: &A, buckets: &[T]) -> Vec<T> {
+        self.rows
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .zip(buckets)
+                    .fold(arithmetic.number(0), |sum, (m, b)| {
+                        arithmetic.add(&sum, &arithmetic.mul(m, b))
+                    })
+            })
+            .collect()
+    }
+}
+
+/// The number of fish after `days`, in `O(log days)` matrix multiplications.
+pub fn count_with<A: Arithmetic>(
+    arithmetic: &A,
+    input: &[u8],
+    days: u64,
+    lifecycle: Lifecycle,
+) -> A::Value {
+    let layout = Layout::new(lifecycle, input);
+    let buckets = layout
+        .initial(input)
+        .into_iter()
+        .map(|n| arithmetic.number(n))
+        .collect::<Vec<_>>();
+    Matrix::transition(arithmetic, &layout)
+        .power(arithmetic, days)
+        .apply(arithmetic, &buckets)
+        .iter()
+        .fold(arithmetic.number(0), |sum, n| arithmetic.add(&sum, n))
+}
+
+/// The exact number of fish after `days`. The count gains about one bit every eight days, so
+/// for millions of days `count_modulo` is the better choice.
+pub fn count_exact(input: &[u8], days: u64, lifecycle: Lifecycle) -> BigUint {
+    count_with(&Exact, input, days, lifecycle)
+}
+
+/// The number of fish after `days` modulo `modulus`, for any number of days.
+pub fn count_modulo(input: &[u8], days: u64, modulus: u64, lifecycle: Lifecycle) -> u64 {
+    assert!(modulus > 0, "The modulus must be positive.");
+    count_with(&Modulo(modulus), input, days, lifecycle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixtures::EXAMPLE_DATA, solve2};
+    const PRIME: u64 = 1_000_000_007;
+
+    #[test]
+    fn test_count_exact() {
+        for days in [0, 1, 18, 80, 256] {
+            assert_eq!(
+                count_exact(&EXAMPLE_DATA, days, Lifecycle::default()),
+                BigUint::from(solve2(&EXAMPLE_DATA, days as u16))
+            );
+        }
+        // far beyond what fits into a u128
+        let big = count_exact(&EXAMPLE_DATA, 2000, Lifecycle::default());
+        assert!(big.bits() > 128);
+        assert_eq!(
+            count_modulo(&EXAMPLE_DATA, 2000, PRIME, Lifecycle::default()),
+            (big % PRIME).to_u64_digits()[0]
+        );
+    }
+
+    #[test]
+    fn test_count_modulo() {
+        assert_eq!(
+            count_modulo(&EXAMPLE_DATA, 256, PRIME, Lifecycle::default()),
+            26984457539 % PRIME
+        );
+        assert_eq!(count_modulo(&EXAMPLE_DATA, 256, 1, Lifecycle::default()), 0);
+        // the bucket rotation of solve2, modulo the prime
+        let mut buckets = [0_u64, 1, 1, 2, 1, 0, 0, 0, 0];
+        for _ in 0..100_000 {
+            buckets.rotate_left(1);
+            buckets[6] = (buckets[6] + buckets[8]) % PRIME;
+        }
+        assert_eq!(
+            count_modulo(&EXAMPLE_DATA, 100_000, PRIME, Lifecycle::default()),
+            buckets.iter().sum::<u64>() % PRIME
+        );
+        // a trillion days in a few dozen multiplications
+        let far = count_modulo(
+            &EXAMPLE_DATA,
+            1_000_000_000_000,
+            PRIME,
+            Lifecycle::default(),
+        );
+        assert!(far < PRIME);
+        // the largest 64 bit prime doesn't overflow the intermediate products
+        let large = count_modulo(
+            &EXAMPLE_DATA,
+            1_000_000,
+            18_446_744_073_709_551_557,
+            Lifecycle::default(),
+        );
+        assert!(large > 0);
+    }
+
+    #[test]
+    fn test_power() {
+        let layout = Layout::new(Lifecycle::default(), &EXAMPLE_DATA);
+        let transition = Matrix::transition(&Exact, &layout);
+        let week = transition.power(&Exact, 7);
+        let mut step = Matrix::identity(&Exact, 9);
+        for _ in 0..7 {
+            step = step.multiply(&Exact, &transition);
+        }
+        assert_eq!(week, step);
+        assert_eq!(transition.power(&Exact, 0), Matrix::identity(&Exact, 9));
+    }
+}
@@ -0,0 +1,167 @@
+use crate::{lifecycle::Lifecycle, series::Population};
+use num_bigint::BigUint;
+use std::fmt;
+
+/// What to do once the number of fish doesn't fit into a `u128`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    /// Stop with an `OverflowError`.
+    Error,
+    /// Go on counting with arbitrary precision.
+    BigInt,
+}
+
+/// The first day on which the number of fish doesn't fit into a `u128`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OverflowError {
+    pub day: u64,
+}
+
+impl fmt::Display for OverflowError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "The number of fish overflows a u128 on day {}.",
+            self.day
+        )
+    }
+}
+
+impl std::error::Error for OverflowError {}
+
+/// A number of fish, only as large as it needs to be.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Count {
+    Small(u128),
+    Big(BigUint),
+}
+
+impl fmt::Display for Count {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Count::Small(n) => write!(f, "{}", n),
+            Count::Big(n) => write!(f, "{}", n),
+        }
+    }
+}
+
+impl From<Count> for BigUint {
+    fn from(count: Count) -> BigUint {
+        match count {
+            Count::Small(n) => BigUint::from(n),
+            Count::Big(n) => n,
+        }
+    }
+}
+
+/// The sum of the values, `None` if it doesn't fit into a `u128`.
+pub fn checked_sum<'a>(values: impl IntoIterator<Item = &'a u128>) -> Option<u128> {
+    values
+        .into_iter()
+        .try_fold(0_u128, |sum, &n| sum.checked_add(n))
+}
+
+/// The number of fish after `days`, using the bucket model of `solve2` without ever wrapping
+/// around. With `Overflow::BigInt` the counting goes on with arbitrary precision from the day
+/// the counts get too large.
+pub fn count(
+    input: &[u8],
+    days: u64,
+    lifecycle: Lifecycle,
+    overflow: Overflow,
+) -> Result<Count, OverflowError> {
+    let mut population = Population::new(input, lifecycle);
+    let error = loop {
+        // the groups may still fit when their sum doesn't
+        let total = match population.total() {
+            Some(total) => total,
+            None => {
+                break OverflowError {
+                    day: population.day(),
+                }
+            }
+        };
+        if population.day() >= days {
+            return Ok(Count::Small(total));
+        }
+        if let Err(error) = population.try_advance() {
+            break error;
+        }
+    };
+    match overflow {
+        Overflow::Error => Err(error),
+        Overflow::BigInt => Ok(Count::Big(count_big(&population, days))),
+    }
+}
+
+/// Carries on from the population's current day up to `days` with big integers.
+fn count_big(population: &Population, days: u64) -> BigUint {
+    let mut groups = population
+        .groups()
+        .iter()
+        .map(|&n| BigUint::from(n))
+        .collect::<Vec<_>>();
+    for _ in population.day()..days {
+        groups = step_big(&groups, population.transitions());
+    }
+    groups.iter().sum()
+}
+
+/// The groups a day later, following the transitions of a `Population`.
+pub fn step_big(groups: &[BigUint], transitions: &[(usize, usize)]) -> Vec<BigUint> {
+    let mut next = vec![BigUint::default(); groups.len()];
+    for &(from, to) in transitions {
+        next[to] += &groups[from];
+    }
+    next
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixtures::EXAMPLE_DATA, matrix::count_exact};
+
+    #[test]
+    fn test_checked_sum() {
+        assert_eq!(checked_sum(&[1, 2, 3]), Some(6));
+        assert_eq!(checked_sum(&[u128::MAX, 0]), Some(u128::MAX));
+        assert_eq!(checked_sum(&[u128::MAX, 1]), None);
+    }
+
+    #[test]
+    fn test_count() {
+        let lifecycle = Lifecycle::default();
+        assert_eq!(
+            count(&EXAMPLE_DATA, 256, lifecycle, Overflow::Error),
+            Ok(Count::Small(26984457539))
+        );
+        // the last day that fits, counted both ways
+        let day = match count(&EXAMPLE_DATA, 2000, lifecycle, Overflow::Error) {
+            Err(OverflowError { day }) => day,
+            Ok(count) => panic!("{} fish fit into a u128", count),
+        };
+        assert!(matches!(
+            count(&EXAMPLE_DATA, day - 1, lifecycle, Overflow::Error),
+            Ok(Count::Small(_))
+        ));
+        assert!(count_exact(&EXAMPLE_DATA, day - 1, lifecycle).bits() <= 128);
+        assert!(count_exact(&EXAMPLE_DATA, day, lifecycle).bits() > 128);
+
+        let big = count(&EXAMPLE_DATA, 2000, lifecycle, Overflow::BigInt).unwrap();
+        assert!(matches!(big, Count::Big(_)));
+        assert_eq!(
+            BigUint::from(big),
+            count_exact(&EXAMPLE_DATA, 2000, lifecycle)
+        );
+        assert_eq!(
+            OverflowError { day: 7 }.to_string(),
+            "The number of fish overflows a u128 on day 7."
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "overflows a u128")]
+    fn test_solve2_overflow() {
+        crate::solve2(&EXAMPLE_DATA, 2000);
+    }
+}
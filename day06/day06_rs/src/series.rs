@@ -0,0 +1,305 @@
+use crate::{
+    lifecycle::{Layout, Lifecycle},
+    precision::{checked_sum, step_big, Overflow, OverflowError},
+};
+use num_bigint::BigUint;
+use std::fmt;
+
+/// The population on one day, day 0 being the input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Day<N = u128> {
+    pub day: u64,
+    pub total: N,
+    /// Number of fish per timer.
+    pub timers: Vec<N>,
+}
+
+impl Day {
+    pub fn into_big(self) -> Day<BigUint> {
+        Day {
+            day: self.day,
+            total: BigUint::from(self.total),
+            timers: self.timers.into_iter().map(BigUint::from).collect(),
+        }
+    }
+}
+
+/// The population day after day, using the bucket model of `solve2`. The iterator goes on
+/// even once all fish died, and ends with an `OverflowError` once the counts don't fit into a
+/// `u128`.
+pub struct Population {
+    layout: Layout,
+    transitions: Vec<(usize, usize)>,
+    groups: Vec<u128>,
+    day: u64,
+    started: bool,
+    overflowed: bool,
+}
+
+impl Population {
+    pub fn new(input: &[u8], lifecycle: Lifecycle) -> Self {
+        let layout = Layout::new(lifecycle, input);
+        let groups = layout
+            .initial(input)
+            .into_iter()
+            .map(|n| n as u128)
+            .collect();
+        Self {
+            transitions: layout.transitions(),
+            layout,
+            groups,
+            day: 0,
+            started: false,
+            overflowed: false,
+        }
+    }
+    pub fn day(&self) -> u64 {
+        self.day
+    }
+    /// Number of fish per group of the layout on the current day.
+    pub fn groups(&self) -> &[u128] {
+        &self.groups
+    }
+    pub fn transitions(&self) -> &[(usize, usize)] {
+        &self.transitions
+    }
+    /// Number of fish on the current day, `None` if it doesn't fit into a `u128`.
+    pub fn total(&self) -> Option<u128> {
+        checked_sum(&self.groups)
+    }
+    /// Moves on to the next day, unless a count would overflow. The population stays on the
+    /// current day then.
+    pub fn try_advance(&mut self) -> Result<(), OverflowError> {
+        let mut next = vec![0_u128; self.groups.len()];
+        for &(from, to) in self.transitions.iter() {
+            next[to] = next[to]
+                .checked_add(self.groups[from])
+                .ok_or(OverflowError { day: self.day + 1 })?;
+        }
+        self.groups = next;
+        self.day += 1;
+        Ok(())
+    }
+    fn current(&self) -> Result<Day, OverflowError> {
+        let error = OverflowError { day: self.day };
+        let mut timers = vec![0_u128; self.layout.timers()];
+        for (group, &n) in self.groups.iter().enumerate() {
+            let timer = &mut timers[self.layout.timer(group)];
+            *timer = timer.checked_add(n).ok_or(error)?;
+        }
+        Ok(Day {
+            day: self.day,
+            total: checked_sum(&timers).ok_or(error)?,
+            timers,
+        })
+    }
+}
+
+impl Iterator for Population {
+    type Item = Result<Day, OverflowError>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.overflowed {
+            return None;
+        }
+        // only move on when asked for the next day, it might not fit anymore
+        let day = if self.started {
+            self.try_advance().and_then(|_| self.current())
+        } else {
+            self.current()
+        };
+        self.started = true;
+        self.overflowed = day.is_err();
+        Some(day)
+    }
+}
+
+/// The population on every day up to `days`. With `Overflow::BigInt` the counting goes on
+/// with arbitrary precision from the first day that doesn't fit into a `u128`.
+pub fn series(
+    input: &[u8],
+    lifecycle: Lifecycle,
+    days: usize,
+    overflow: Overflow,
+) -> Result<Vec<Day<BigUint>>, OverflowError> {
+    let mut population = Population::new(input, lifecycle);
+    let mut series = Vec::with_capacity(days + 1);
+    for day in population.by_ref().take(days + 1) {
+        match (day, overflow) {
+            (Ok(day), _) => series.push(day.into_big()),
+            (Err(error), Overflow::Error) => return Err(error),
+            (Err(_), Overflow::BigInt) => break,
+        }
+    }
+    // the population stays on the last day that fit, or moved on to the first that didn't
+    let mut groups = population
+        .groups()
+        .iter()
+        .map(|&n| BigUint::from(n))
+        .collect::<Vec<_>>();
+    let mut day = population.day();
+    while series.len() <= days {
+        if day < series.len() as u64 {
+            groups = step_big(&groups, population.transitions());
+            day += 1;
+        }
+        let mut timers = vec![BigUint::default(); population.layout.timers()];
+        for (group, n) in groups.iter().enumerate() {
+            timers[population.layout.timer(group)] += n;
+        }
+        series.push(Day {
+            day,
+            total: timers.iter().sum(),
+            timers,
+        });
+    }
+    Ok(series)
+}
+
+/// The first day with more than `threshold` fish, looking at most `max_days` days ahead.
+pub fn first_day_above(
+    input: &[u8],
+    lifecycle: Lifecycle,
+    threshold: u128,
+    max_days: u64,
+) -> Option<u64> {
+    let mut population = Population::new(input, lifecycle);
+    while population.day <= max_days {
+        // a total too large for a u128 is above any threshold
+        if population.total().is_none_or(|total| total > threshold) {
+            return Some(population.day);
+        }
+        if population.try_advance().is_err() {
+            return (population.day < max_days).then_some(population.day + 1);
+        }
+    }
+    None
+}
+
+/// One line per day with the total and the number of fish per timer.
+pub fn to_csv<N: fmt::Display>(days: &[Day<N>]) -> String {
+    let timers = days.first().map_or(0, |d| d.timers.len());
+    let mut csv = String::from("day,total");
+    for timer in 0..timers {
+        csv.push_str(&format!(",timer{}", timer));
+    }
+    csv.push('\n');
+    for day in days {
+        csv.push_str(&format!("{},{}", day.day, day.total));
+        for n in day.timers.iter() {
+            csv.push_str(&format!(",{}", n));
+        }
+        csv.push('\n');
+    }
+    csv
+}
+
+/// An array with an object per day. Counts are written as plain numbers, so readers using
+/// doubles lose precision beyond 2^53 fish.
+pub fn to_json<N: fmt::Display>(days: &[Day<N>]) -> String {
+    let days = days
+        .iter()
+        .map(|day| {
+            let timers = day.timers.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+            format!(
+                "{{\"day\":{},\"total\":{},\"timers\":[{}]}}",
+                day.day,
+                day.total,
+                timers.join(",")
+            )
+        })
+        .collect::<Vec<_>>();
+    format!("[{}]", days.join(",\n "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixtures::EXAMPLE_DATA, matrix::count_exact, precision, solve1_with, solve2};
+
+    #[test]
+    fn test_population() {
+        let days = Population::new(&EXAMPLE_DATA, Lifecycle::default())
+            .take(81)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(days[0].timers, vec![0, 1, 1, 2, 1, 0, 0, 0, 0]);
+        // day 2 of the puzzle text: 1,2,1,6,0,8
+        assert_eq!(days[2].timers, vec![1, 2, 1, 0, 0, 0, 1, 0, 1]);
+        assert_eq!(days[18].total, 26);
+        assert_eq!(days[80].total, 5934);
+        assert!(days
+            .iter()
+            .all(|d| d.total == solve2(&EXAMPLE_DATA, d.day as u16)));
+
+        // with mortality the timers of old fish are added up as well
+        let mortal = Lifecycle {
+            max_age: Some(3),
+            ..Lifecycle::default()
+        };
+        let days = Population::new(&EXAMPLE_DATA, mortal)
+            .take(4)
+            .map(|d| d.unwrap().total)
+            .collect::<Vec<_>>();
+        assert_eq!(days, vec![5, 5, 6, 2]);
+        assert_eq!(days[3], solve1_with(&EXAMPLE_DATA, 3, mortal) as u128);
+    }
+
+    #[test]
+    fn test_overflow() {
+        let lifecycle = Lifecycle::default();
+        let error = precision::count(&EXAMPLE_DATA, 1000, lifecycle, Overflow::Error).unwrap_err();
+        let days = Population::new(&EXAMPLE_DATA, lifecycle).collect::<Vec<_>>();
+        assert_eq!(days.len() as u64, error.day + 1);
+        assert_eq!(days.last(), Some(&Err(error)));
+        assert_eq!(
+            series(&EXAMPLE_DATA, lifecycle, 1000, Overflow::Error),
+            Err(error)
+        );
+
+        let days = series(&EXAMPLE_DATA, lifecycle, 1000, Overflow::BigInt).unwrap();
+        assert_eq!(days.len(), 1001);
+        for day in [0, 80, error.day - 1, error.day, 1000] {
+            let expected = count_exact(&EXAMPLE_DATA, day, lifecycle);
+            assert_eq!(days[day as usize].day, day);
+            assert_eq!(days[day as usize].total, expected);
+        }
+        let timers = days[1000].timers.iter().sum::<BigUint>();
+        assert_eq!(timers, days[1000].total);
+    }
+
+    #[test]
+    fn test_first_day_above() {
+        let lifecycle = Lifecycle::default();
+        assert_eq!(first_day_above(&EXAMPLE_DATA, lifecycle, 4, 100), Some(0));
+        assert_eq!(first_day_above(&EXAMPLE_DATA, lifecycle, 25, 100), Some(18));
+        assert_eq!(
+            first_day_above(&EXAMPLE_DATA, lifecycle, 5934, 100),
+            Some(81)
+        );
+        assert_eq!(first_day_above(&EXAMPLE_DATA, lifecycle, 5934, 80), None);
+        let mortal = Lifecycle {
+            max_age: Some(3),
+            ..lifecycle
+        };
+        assert_eq!(first_day_above(&EXAMPLE_DATA, mortal, 10, 1000), None);
+    }
+
+    #[test]
+    fn test_export() {
+        let days = series(&[0, 1], Lifecycle::default(), 1, Overflow::Error).unwrap();
+        assert_eq!(
+            to_csv(&days),
+            "day,total,timer0,timer1,timer2,timer3,timer4,timer5,timer6,timer7,timer8
+0,2,1,1,0,0,0,0,0,0,0
+1,3,1,0,0,0,0,0,1,0,1
+"
+        );
+        assert_eq!(
+            to_json(&days),
+            "[{\"day\":0,\"total\":2,\"timers\":[1,1,0,0,0,0,0,0,0]},
+ {\"day\":1,\"total\":3,\"timers\":[1,0,0,0,0,0,1,0,1]}]"
+        );
+        assert_eq!(to_csv::<u128>(&[]), "day,total\n");
+        assert_eq!(to_json::<u128>(&[]), "[]");
+    }
+}
@@ -0,0 +1,595 @@
+use crate::{
+    lifecycle::Lifecycle,
+    precision::{checked_sum, Overflow, OverflowError},
+};
+use num_bigint::BigUint;
+use num_traits::ToPrimitive;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rand_distr::Binomial;
+use std::thread;
+
+/// A distribution of small whole numbers.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Distribution {
+    /// Always the same value.
+    Fixed(u32),
+    /// Every value from the first to the second one with the same probability.
+    Uniform(u32, u32),
+    /// Values with relative weights.
+    Weighted(Vec<(u32, f64)>),
+}
+
+impl Distribution {
+    /// Parses `6`, `5-7` or weights like `0:0.2,1:0.8`.
+    pub fn from_string(s: &str) -> Self {
+        let s = s.trim();
+        if s.contains(':') {
+            let weights = s
+                .split(',')
+                .map(|part| {
+                    let (value, weight) = part.split_once(':').unwrap();
+                    (
+                        value.trim().parse().unwrap(),
+                        weight.trim().parse().unwrap(),
+                    )
+                })
+                .collect();
+            Distribution::Weighted(weights)
+        } else if let Some((low, high)) = s.split_once('-') {
+            Distribution::Uniform(low.trim().parse().unwrap(), high.trim().parse().unwrap())
+        } else {
+            Distribution::Fixed(s.parse().unwrap())
+        }
+    }
+    /// Every possible value with its probability.
+    pub fn outcomes(&self) -> Vec<(u32, f64)> {
+        match self {
+            Distribution::Fixed(value) => vec![(*value, 1.0)],
+            Distribution::Uniform(low, high) => {
+                assert!(low <= high, "Empty range {}-{}.", low, high);
+                let p = 1.0 / (high - low + 1) as f64;
+                (*low..=*high).map(|v| (v, p)).collect()
+            }
+            Distribution::Weighted(weights) => {
+                let total = weights.iter().map(|(_, w)| w).sum::<f64>();
+                assert!(total > 0.0, "The weights must add up to more than 0.");
+                weights.iter().map(|&(v, w)| (v, w / total)).collect()
+            }
+        }
+    }
+    pub fn max(&self) -> u32 {
+        self.outcomes().iter().map(|&(v, _)| v).max().unwrap_or(0)
+    }
+}
+
+/// A lanternfish model where every fish draws its own numbers. With the default, where every
+/// distribution always gives the same value and no fish dies, it is the puzzle's model.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Model {
+    /// Number of fish spawned each time a timer runs out.
+    pub spawns: Distribution,
+    /// Timer of a fish after it spawned, the days until it spawns again.
+    pub gestation: Distribution,
+    /// Timer of a newborn fish.
+    pub newborn: Distribution,
+    /// Probability that a fish lives through a day.
+    pub survival: f64,
+    /// Days a fish has to live before it spawns, as in `Lifecycle`.
+    pub maturity: Option<u16>,
+    /// Fish die once they are this many days old, as in `Lifecycle`.
+    pub max_age: Option<u16>,
+}
+
+impl Model {
+    /// The lifecycle with fixed timers, one fish per spawn and no random deaths.
+    pub fn from_lifecycle(lifecycle: Lifecycle) -> Self {
+        Self {
+            spawns: Distribution::Fixed(1),
+            gestation: Distribution::Fixed(lifecycle.reset as u32),
+            newborn: Distribution::Fixed(lifecycle.newborn as u32),
+            survival: 1.0,
+            maturity: lifecycle.maturity,
+            max_age: lifecycle.max_age,
+        }
+    }
+    fn lifecycle(&self) -> Lifecycle {
+        Lifecycle {
+            maturity: self.maturity,
+            max_age: self.max_age,
+            ..Lifecycle::default()
+        }
+    }
+}
+
+impl Default for Model {
+    fn default() -> Self {
+        Self::from_lifecycle(Lifecycle::default())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StochasticOptions {
+    pub trials: usize,
+    pub days: usize,
+    /// Seeds the binomial draws of survivors, timers and spawns. Trial `n` draws from its own
+    /// generator seeded with `seed + n`, and stops drawing once it counts in big integers.
+    pub seed: u64,
+    pub threads: usize,
+    /// What a trial does once its fish don't fit into a `u128`.
+    pub overflow: Overflow,
+}
+
+impl Default for StochasticOptions {
+    fn default() -> Self {
+        Self {
+            trials: 1000,
+            days: 80,
+            seed: 0,
+            threads: 1,
+            overflow: Overflow::BigInt,
+        }
+    }
+}
+
+/// The population on one day over all trials.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DayStats {
+    pub day: usize,
+    pub mean: f64,
+    /// One value for every requested percentile.
+    pub percentiles: Vec<BigUint>,
+}
+
+/// A number of fish in a trial. As a `u128` every fish draws at random, as long as a group
+/// fits into a `u64`, larger groups and `BigUint`s take the expected values instead.
+trait Amount: Clone + Default + PartialEq {
+    /// How many of these fish come out on top with probability `p` each.
+    fn portion(&self, p: f64, rng: &mut StdRng) -> Self;
+    fn checked_add(&self, n: &Self) -> Option<Self>;
+    fn checked_mul(&self, n: u32) -> Option<Self>;
+    fn sub(&self, n: &Self) -> Self;
+}
+
+impl Amount for u128 {
+    fn portion(&self, p: f64, rng: &mut StdRng) -> Self {
+        match u64::try_from(*self) {
+            Ok(n) => binomial(n, p, rng) as u128,
+            Err(_) if p >= 1.0 => *self,
+            Err(_) => ((*self as f64 * p.max(0.0)) as u128).min(*self),
+        }
+    }
+    fn checked_add(&self, n: &Self) -> Option<Self> {
+        u128::checked_add(*self, *n)
+    }
+    fn checked_mul(&self, n: u32) -> Option<Self> {
+        u128::checked_mul(*self, n as u128)
+    }
+    fn sub(&self, n: &Self) -> Self {
+        self - n
+    }
+}
+
+impl Amount for BigUint {
+    fn portion(&self, p: f64, _rng: &mut StdRng) -> Self {
+        if p >= 1.0 {
+            self.clone()
+        } else {
+            // p in 53 bits, as precise as it is
+            (self * BigUint::from((p.max(0.0) * (1_u64 << 53) as f64) as u64)) >> 53
+        }
+    }
+    fn checked_add(&self, n: &Self) -> Option<Self> {
+        Some(self + n)
+    }
+    fn checked_mul(&self, n: u32) -> Option<Self> {
+        Some(self * n)
+    }
+    fn sub(&self, n: &Self) -> Self {
+        self - n
+    }
+}
+
+/// How many of `n` fish, each drawing from `outcomes`, draw each value.
+fn split<N: Amount>(n: &N, outcomes: &[(u32, f64)], rng: &mut StdRng) -> Vec<(u32, N)> {
+    let mut left = n.clone();
+    let mut mass = 1.0;
+    let mut counts = Vec::with_capacity(outcomes.len());
+    for (i, &(value, p)) in outcomes.iter().enumerate() {
+        let k = if i + 1 == outcomes.len() {
+            left.clone()
+        } else {
+            left.portion((p / mass).min(1.0), rng)
+        };
+        left = left.sub(&k);
+        counts.push((value, k));
+        mass -= p;
+    }
+    counts
+}
+
+fn binomial(n: u64, p: f64, rng: &mut StdRng) -> u64 {
+    if n == 0 || p <= 0.0 {
+        0
+    } else if p >= 1.0 {
+        n
+    } else {
+        rng.sample(Binomial::new(n, p).unwrap())
+    }
+}
+
+fn add<N: Amount>(count: &mut N, n: &N) -> Option<()> {
+    *count = count.checked_add(n)?;
+    Some(())
+}
+
+/// The outcomes of a model, with fish grouped by age and timer, ages only as far as maturity
+/// and mortality need them.
+struct Trial {
+    spawns: Vec<(u32, f64)>,
+    gestation: Vec<(u32, f64)>,
+    newborn: Vec<(u32, f64)>,
+    survival: f64,
+    lifecycle: Lifecycle,
+    timers: usize,
+    ages: usize,
+}
+
+impl Trial {
+    fn new(input: &[u8], model: &Model) -> Self {
+        let timers = input
+            .iter()
+            .map(|&t| t as u32)
+            .chain([model.gestation.max(), model.newborn.max()])
+            .max()
+            .unwrap() as usize
+            + 1;
+        let ages = match (model.max_age, model.maturity) {
+            (Some(max_age), _) => max_age as usize,
+            (None, Some(maturity)) => maturity as usize + 1,
+            (None, None) => 1,
+        };
+        Self {
+            spawns: model.spawns.outcomes(),
+            gestation: model.gestation.outcomes(),
+            newborn: model.newborn.outcomes(),
+            survival: model.survival,
+            lifecycle: model.lifecycle(),
+            timers,
+            ages,
+        }
+    }
+    /// The group of a fish of the given age and timer, `None` once it is dead.
+    fn group(&self, age: usize, timer: u32) -> Option<usize> {
+        self.lifecycle
+            .is_alive(age)
+            .then(|| age.min(self.ages - 1) * self.timers + timer as usize)
+    }
+    fn initial(&self, input: &[u8]) -> Vec<u128> {
+        let mut counts = vec![0; self.ages * self.timers];
+        for &t in input {
+            if let Some(g) = self.group(self.lifecycle.initial_age() as usize, t as u32) {
+                counts[g] += 1;
+            }
+        }
+        counts
+    }
+    /// The groups a day later, `None` if they don't fit into `N`.
+    fn step<N: Amount>(&self, counts: &[N], rng: &mut StdRng) -> Option<Vec<N>> {
+        let mut next = vec![N::default(); counts.len()];
+        for (g, n) in counts.iter().enumerate() {
+            if *n == N::default() {
+                continue;
+            }
+            let (age, timer) = (g / self.timers, (g % self.timers) as u32);
+            let n = n.portion(self.survival, rng);
+            if timer > 0 {
+                if let Some(to) = self.group(age + 1, timer - 1) {
+                    add(&mut next[to], &n)?;
+                }
+                continue;
+            }
+            // like a single fish, it may spawn on the day it dies
+            if self.lifecycle.is_alive(age + 1) {
+                for (t, k) in split(&n, &self.gestation, rng) {
+                    add(&mut next[self.group(age + 1, t).unwrap()], &k)?;
+                }
+            }
+            if !self.lifecycle.is_mature(age) {
+                continue;
+            }
+            let mut spawned = N::default();
+            for (s, k) in split(&n, &self.spawns, rng) {
+                add(&mut spawned, &k.checked_mul(s)?)?;
+            }
+            for (t, k) in split(&spawned, &self.newborn, rng) {
+                if let Some(to) = self.group(0, t) {
+                    add(&mut next[to], &k)?;
+                }
+            }
+        }
+        Some(next)
+    }
+}
+
+/// The total number of fish on every day of a single trial, day 0 being the input. Once the
+/// fish don't fit into a `u128` any more, the trial either stops with an `OverflowError` or
+/// goes on with big integers and expected values.
+fn run_trial(
+    input: &[u8],
+    model: &Model,
+    days: usize,
+    seed: u64,
+    overflow: Overflow,
+) -> Result<Vec<BigUint>, OverflowError> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let trial = Trial::new(input, model);
+    let mut counts = trial.initial(input);
+    let mut totals = Vec::with_capacity(days + 1);
+    totals.push(BigUint::from(checked_sum(&counts).unwrap()));
+    while totals.len() <= days {
+        let next = trial
+            .step(&counts, &mut rng)
+            .and_then(|next| Some((checked_sum(&next)?, next)));
+        if let Some((total, next)) = next {
+            counts = next;
+            totals.push(BigUint::from(total));
+            continue;
+        }
+        if overflow == Overflow::Error {
+            return Err(OverflowError {
+                day: totals.len() as u64,
+            });
+        }
+        let mut counts = counts.iter().map(|&n| BigUint::from(n)).collect::<Vec<_>>();
+        while totals.len() <= days {
+            counts = trial.step(&counts, &mut rng).unwrap();
+            totals.push(counts.iter().sum());
+        }
+    }
+    Ok(totals)
+}
+
+/// Runs `options.trials` independent trials and sums up the population of every day. The
+/// percentiles are given from 0 to 100 and use the nearest rank. With `Overflow::Error` the
+/// earliest overflow of any trial is returned.
+pub fn simulate(
+    input: &[u8],
+    model: &Model,
+    options: StochasticOptions,
+    percentiles: &[f64],
+) -> Result<Vec<DayStats>, OverflowError> {
+    let threads = options.threads.max(1);
+    let trials = thread::scope(|s| {
+        let handles = (0..threads)
+            .map(|t| {
+                s.spawn(move || {
+                    (t..options.trials)
+                        .step_by(threads)
+                        .map(|trial| {
+                            let seed = options.seed.wrapping_add(trial as u64);
+                            run_trial(input, model, options.days, seed, options.overflow)
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().unwrap())
+            .collect::<Vec<_>>()
+    });
+    if let Some(error) = trials
+        .iter()
+        .filter_map(|t| t.as_ref().err())
+        .min_by_key(|e| e.day)
+    {
+        return Err(*error);
+    }
+    let trials = trials.into_iter().flatten().collect::<Vec<_>>();
+
+    Ok((0..=options.days)
+        .map(|day| {
+            let mut totals = trials.iter().map(|t| &t[day]).collect::<Vec<_>>();
+            totals.sort_unstable();
+            let sum = totals.iter().copied().sum::<BigUint>();
+            let mean = sum.to_f64().unwrap_or(f64::INFINITY) / totals.len().max(1) as f64;
+            let percentiles = percentiles
+                .iter()
+                .map(|&p| {
+                    let rank = (p / 100.0 * totals.len() as f64).ceil() as usize;
+                    totals
+                        .get(rank.max(1) - 1)
+                        .map_or_else(BigUint::default, |&n| n.clone())
+                })
+                .collect();
+            DayStats {
+                day,
+                mean,
+                percentiles,
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fixtures::EXAMPLE_DATA, matrix::count_exact, precision, solve2, solve2_with};
+
+    #[test]
+    fn test_distribution() {
+        assert_eq!(Distribution::from_string("6"), Distribution::Fixed(6));
+        assert_eq!(
+            Distribution::from_string("5-7"),
+            Distribution::Uniform(5, 7)
+        );
+        let weighted = Distribution::from_string("0:1,2:3");
+        assert_eq!(weighted, Distribution::Weighted(vec![(0, 1.0), (2, 3.0)]));
+        assert_eq!(weighted.outcomes(), vec![(0, 0.25), (2, 0.75)]);
+        assert_eq!(weighted.max(), 2);
+        assert_eq!(Distribution::Uniform(5, 7).outcomes().len(), 3);
+
+        let mut rng = StdRng::seed_from_u64(1);
+        let counts = split(
+            &1000_u128,
+            &Distribution::Uniform(5, 7).outcomes(),
+            &mut rng,
+        );
+        assert_eq!(counts.iter().map(|&(_, k)| k).sum::<u128>(), 1000);
+        assert!(counts.iter().all(|&(_, k)| (250..=420).contains(&k)));
+    }
+
+    #[test]
+    fn test_degenerate_model() {
+        let options = StochasticOptions {
+            trials: 3,
+            days: 256,
+            ..StochasticOptions::default()
+        };
+        let stats = simulate(
+            &EXAMPLE_DATA,
+            &Model::default(),
+            options,
+            &[0.0, 50.0, 100.0],
+        )
+        .unwrap();
+        assert_eq!(stats.len(), 257);
+        for day in [0, 18, 80, 256] {
+            let expected = solve2(&EXAMPLE_DATA, day as u16);
+            assert_eq!(stats[day].mean, expected as f64);
+            assert_eq!(stats[day].percentiles, vec![BigUint::from(expected); 3]);
+        }
+
+        for (maturity, max_age) in [(Some(u16::MAX), None), (None, Some(u16::MAX))] {
+            let lifecycle = Lifecycle {
+                maturity,
+                max_age,
+                ..Lifecycle::default()
+            };
+            let model = Model::from_lifecycle(lifecycle);
+            let options = StochasticOptions {
+                trials: 1,
+                days: 20,
+                ..options
+            };
+            let stats = simulate(&[3], &model, options, &[50.0]).unwrap();
+            for day in [5, 20] {
+                let expected = solve2_with(&[3], day as u16, lifecycle);
+                assert_eq!(stats[day].percentiles, vec![BigUint::from(expected)]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_overflow() {
+        let lifecycle = Lifecycle::default();
+        let options = StochasticOptions {
+            trials: 2,
+            days: 1000,
+            overflow: Overflow::Error,
+            ..StochasticOptions::default()
+        };
+        // the same day as the exact count
+        let error = precision::count(&EXAMPLE_DATA, 1000, lifecycle, Overflow::Error).unwrap_err();
+        assert_eq!(
+            simulate(&EXAMPLE_DATA, &Model::default(), options, &[50.0]),
+            Err(error)
+        );
+        let stats = simulate(
+            &EXAMPLE_DATA,
+            &Model::default(),
+            StochasticOptions {
+                overflow: Overflow::BigInt,
+                ..options
+            },
+            &[50.0],
+        )
+        .unwrap();
+        for day in [500, 1000] {
+            let expected = count_exact(&EXAMPLE_DATA, day as u64, lifecycle);
+            assert_eq!(stats[day].percentiles, vec![expected]);
+        }
+
+        // random fish go on with their expected values
+        let model = Model {
+            spawns: Distribution::from_string("1:0.5,2:0.5"),
+            survival: 0.999,
+            ..Model::default()
+        };
+        let stats = simulate(
+            &EXAMPLE_DATA,
+            &model,
+            StochasticOptions {
+                overflow: Overflow::BigInt,
+                ..options
+            },
+            &[0.0, 100.0],
+        )
+        .unwrap();
+        assert!(stats[1000].percentiles[0].bits() > 128);
+        assert!(stats[1000].percentiles[0] <= stats[1000].percentiles[1]);
+    }
+
+    #[test]
+    fn test_simulate() {
+        let model = Model {
+            spawns: Distribution::from_string("0:0.5,1:0.3,2:0.2"),
+            gestation: Distribution::Uniform(5, 7),
+            survival: 0.99,
+            ..Model::default()
+        };
+        let options = StochasticOptions {
+            trials: 40,
+            days: 60,
+            seed: 7,
+            ..StochasticOptions::default()
+        };
+        let stats = simulate(&EXAMPLE_DATA, &model, options, &[5.0, 50.0, 95.0]).unwrap();
+        // the same seed gives the same result on any number of threads
+        let threaded = simulate(
+            &EXAMPLE_DATA,
+            &model,
+            StochasticOptions {
+                threads: 3,
+                ..options
+            },
+            &[5.0, 50.0, 95.0],
+        )
+        .unwrap();
+        assert_eq!(stats, threaded);
+        assert!(stats
+            .iter()
+            .all(|d| d.percentiles[0] <= d.percentiles[1] && d.percentiles[1] <= d.percentiles[2]));
+        assert_ne!(stats[60].percentiles[0], stats[60].percentiles[2]);
+        assert_eq!(stats[0].mean, 5.0);
+
+        // maturity and mortality work as in the deterministic model
+        let mortal = Lifecycle {
+            maturity: Some(10),
+            max_age: Some(20),
+            ..Lifecycle::default()
+        };
+        let stats = simulate(
+            &EXAMPLE_DATA,
+            &Model::from_lifecycle(mortal),
+            StochasticOptions {
+                trials: 2,
+                days: 100,
+                ..options
+            },
+            &[50.0],
+        )
+        .unwrap();
+        for day in [0, 5, 10, 19, 30, 100] {
+            let expected = solve2_with(&EXAMPLE_DATA, day as u16, mortal);
+            assert_eq!(stats[day].percentiles, vec![BigUint::from(expected)]);
+        }
+
+        let extinct = Model {
+            survival: 0.0,
+            ..Model::default()
+        };
+        let stats = simulate(&EXAMPLE_DATA, &extinct, options, &[50.0]).unwrap();
+        assert_eq!(stats[1].mean, 0.0);
+    }
+}
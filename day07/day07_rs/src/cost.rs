@@ -0,0 +1,395 @@
+use std::ops::RangeInclusive;
+
+/// Fuel is counted in a `usize`, larger amounts panic with this message instead of wrapping.
+pub const OVERFLOW: &str = "The fuel doesn't fit into a usize.";
+
+/// How much fuel a crab burns to move a distance.
+pub trait FuelCost {
+    /// Panics with `OVERFLOW` if the fuel doesn't fit into a `usize`.
+    fn fuel(&self, distance: usize) -> usize;
+    /// Whether every step costs at least as much as the one before, which makes the total fuel
+    /// convex in the target position.
+    fn is_convex(&self) -> bool {
+        false
+    }
+    /// The best position and its fuel without searching, if the cost has a closed form.
+    fn closed_form(&self, _positions: &[usize]) -> Option<(usize, usize)> {
+        None
+    }
+    /// The fuel as a sum of terms, so that the fuel of many crabs follows from a few sums over
+    /// their positions.
+    fn terms(&self) -> Option<Terms> {
+        None
+    }
+}
+
+/// Moving n steps costs the sum of `a·m² + b·m` over every term `(from, a, b)` with
+/// `m = n - from` steps past `from`, divided by `divisor`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Terms {
+    pub terms: Vec<(usize, i128, i128)>,
+    pub divisor: i128,
+}
+
+/// Every step costs one fuel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Linear;
+
+/// The n-th step costs n fuel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Triangular;
+
+/// Moving n steps costs n² fuel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quadratic;
+
+/// Steps cost a different rate depending on how far the crab already went.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Piecewise {
+    /// `(from, rate)` sorted by `from`: every step after the first `from` steps costs `rate`,
+    /// up to the next `from`.
+    rates: Vec<(usize, usize)>,
+}
+
+impl FuelCost for Linear {
+    fn fuel(&self, distance: usize) -> usize {
+        distance
+    }
+    fn is_convex(&self) -> bool {
+        true
+    }
+    fn closed_form(&self, positions: &[usize]) -> Option<(usize, usize)> {
+        // any position between the two middle crabs is best, the lower median is the first
+        let mut sorted = positions.to_vec();
+        let (_, &mut median, _) = sorted.select_nth_unstable((positions.len() - 1) / 2);
+        Some((median, total(positions, median, self)))
+    }
+    fn terms(&self) -> Option<Terms> {
+        Some(Terms {
+            terms: vec![(0, 0, 1)],
+            divisor: 1,
+        })
+    }
+}
+
+impl FuelCost for Triangular {
+    fn fuel(&self, distance: usize) -> usize {
+        let distance = distance as u128;
+        usize::try_from(distance * (distance + 1) / 2).expect(OVERFLOW)
+    }
+    fn is_convex(&self) -> bool {
+        true
+    }
+    fn closed_form(&self, positions: &[usize]) -> Option<(usize, usize)> {
+        // the best real position is at most half a step from the mean and the cost is convex,
+        // so the best whole position is right next to the mean
+        let mean = mean(positions);
+        Some(scan(positions, near(positions, mean, 1, 2), self))
+    }
+    fn terms(&self) -> Option<Terms> {
+        Some(Terms {
+            terms: vec![(0, 1, 1)],
+            divisor: 2,
+        })
+    }
+}
+
+impl FuelCost for Quadratic {
+    fn fuel(&self, distance: usize) -> usize {
+        distance.checked_mul(distance).expect(OVERFLOW)
+    }
+    fn is_convex(&self) -> bool {
+        true
+    }
+    fn closed_form(&self, positions: &[usize]) -> Option<(usize, usize)> {
+        // the mean is the best real position, so one of its neighbours is the best whole one
+        let mean = mean(positions);
+        Some(scan(positions, near(positions, mean, 0, 1), self))
+    }
+    fn terms(&self) -> Option<Terms> {
+        Some(Terms {
+            terms: vec![(0, 1, 0)],
+            divisor: 1,
+        })
+    }
+}
+
+impl Piecewise {
+    pub fn new(mut rates: Vec<(usize, usize)>) -> Self {
+        assert!(
+            !rates.is_empty(),
+            "A piecewise cost needs at least one rate."
+        );
+        rates.sort_unstable();
+        Self { rates }
+    }
+    /// Parses rates like `0:1,10:3`, where the first ten steps cost one and all further steps
+    /// cost three.
+    pub fn from_string(s: &str) -> Self {
+        let rates = s
+            .trim()
+            .split(',')
+            .map(|part| {
+                let (from, rate) = part.split_once(':').unwrap();
+                (from.trim().parse().unwrap(), rate.trim().parse().unwrap())
+            })
+            .collect();
+        Self::new(rates)
+    }
+}
+
+impl FuelCost for Piecewise {
+    fn fuel(&self, distance: usize) -> usize {
+        let mut fuel: usize = 0;
+        for (i, &(from, rate)) in self.rates.iter().enumerate() {
+            let to = self
+                .rates
+                .get(i + 1)
+                .map_or(distance, |&(next, _)| next.min(distance));
+            fuel = to
+                .saturating_sub(from)
+                .checked_mul(rate)
+                .and_then(|n| fuel.checked_add(n))
+                .expect(OVERFLOW);
+        }
+        fuel
+    }
+    fn is_convex(&self) -> bool {
+        self.rates.windows(2).all(|w| w[0].1 <= w[1].1)
+    }
+    fn terms(&self) -> Option<Terms> {
+        // every rate adds what it costs more than the one before
+        let mut previous = 0;
+        let terms = self
+            .rates
+            .iter()
+            .map(|&(from, rate)| {
+                let term = (from, 0, rate as i128 - previous);
+                previous = rate as i128;
+                term
+            })
+            .collect();
+        Some(Terms { terms, divisor: 1 })
+    }
+}
+
+/// Parses `linear`, `triangular`, `quadratic` or the rates of a `Piecewise` cost.
+pub fn from_string(s: &str) -> Box<dyn FuelCost> {
+    match s.trim() {
+        "linear" => Box::new(Linear),
+        "triangular" => Box::new(Triangular),
+        "quadratic" => Box::new(Quadratic),
+        rates => Box::new(Piecewise::from_string(rates)),
+    }
+}
+
+/// How `optimize` finds the best position for a cost.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    ClosedForm,
+    Ternary,
+    Scan,
+}
+
+/// The fuel all crabs need to move to `to`, panics with `OVERFLOW` if it doesn't fit.
+pub fn total(positions: &[usize], to: usize, cost: &(impl FuelCost + ?Sized)) -> usize {
+    positions
+        .iter()
+        .try_fold(0_usize, |sum, &p| {
+            sum.checked_add(cost.fuel(p.abs_diff(to)))
+        })
+        .expect(OVERFLOW)
+}
+
+fn mean(positions: &[usize]) -> usize {
+    let sum = positions.iter().map(|&p| p as u128).sum::<u128>();
+    (sum / positions.len() as u128) as usize
+}
+
+/// Every position from the first crab to the last.
+pub fn range(positions: &[usize]) -> RangeInclusive<usize> {
+    let min = *positions.iter().min().unwrap();
+    let max = *positions.iter().max().unwrap();
+    min..=max
+}
+
+/// The positions from `below` under to `above` over `around`, as far as there are crabs.
+fn near(positions: &[usize], around: usize, below: usize, above: usize) -> RangeInclusive<usize> {
+    let range = range(positions);
+    around.saturating_sub(below).max(*range.start())..=(around + above).min(*range.end())
+}
+
+/// The best position within `candidates`, trying every one of them. On a tie the first one is
+/// taken.
+pub fn scan(
+    positions: &[usize],
+    candidates: impl Iterator<Item = usize>,
+    cost: &(impl FuelCost + ?Sized),
+) -> (usize, usize) {
+    let mut best: Option<(usize, usize)> = None;
+    for n in candidates {
+        let fuel = total(positions, n, cost);
+        if best.is_none_or(|(_, least)| fuel < least) {
+            best = Some((n, fuel));
+        }
+    }
+    best.unwrap()
+}
+
+/// The first position with the least `total` fuel, for a total that falls until the best
+/// position and never falls after it, as it does for convex costs. The range is cut down on
+/// the slope at its middle.
+pub fn ternary(range: RangeInclusive<usize>, total: impl Fn(usize) -> usize) -> (usize, usize) {
+    let (mut low, mut high) = range.into_inner();
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if total(mid + 1) < total(mid) {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+    (low, total(low))
+}
+
+/// The position all crabs move to with the least fuel, and that fuel. On a tie the lowest
+/// position is taken.
+pub fn optimize(positions: &[usize], cost: &(impl FuelCost + ?Sized)) -> (usize, usize) {
+    optimize_with(positions, cost).1
+}
+
+/// Like `optimize`, also telling which strategy found the position.
+pub fn optimize_with(
+    positions: &[usize],
+    cost: &(impl FuelCost + ?Sized),
+) -> (Strategy, (usize, usize)) {
+    if let Some(best) = cost.closed_form(positions) {
+        (Strategy::ClosedForm, best)
+    } else if cost.is_convex() {
+        (
+            Strategy::Ternary,
+            ternary(range(positions), |to| total(positions, to, cost)),
+        )
+    } else {
+        (Strategy::Scan, scan(positions, range(positions), cost))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{random, EXAMPLE_DATA};
+
+    /// The fuel for a distance, from the terms.
+    fn terms_fuel(terms: &Terms, distance: usize) -> usize {
+        let sum = terms
+            .terms
+            .iter()
+            .map(|&(from, a, b)| {
+                let m = distance.saturating_sub(from) as i128;
+                a * m * m + b * m
+            })
+            .sum::<i128>();
+        (sum / terms.divisor) as usize
+    }
+
+    /// The same cost, but without its closed form.
+    struct Searched<C>(C);
+
+    impl<C: FuelCost> FuelCost for Searched<C> {
+        fn fuel(&self, distance: usize) -> usize {
+            self.0.fuel(distance)
+        }
+        fn is_convex(&self) -> bool {
+            self.0.is_convex()
+        }
+    }
+
+    #[test]
+    fn test_fuel() {
+        assert_eq!(Linear.fuel(8), 8);
+        assert_eq!(Triangular.fuel(8), 36);
+        assert_eq!(Quadratic.fuel(8), 64);
+        let piecewise = Piecewise::from_string("10:3, 0:1");
+        assert_eq!(piecewise, Piecewise::new(vec![(0, 1), (10, 3)]));
+        assert_eq!(piecewise.fuel(0), 0);
+        assert_eq!(piecewise.fuel(10), 10);
+        assert_eq!(piecewise.fuel(12), 16);
+        assert!(piecewise.is_convex());
+        // a first stretch for free, then cheaper steps after a while
+        let concave = Piecewise::from_string("2:5,6:1");
+        assert_eq!(concave.fuel(1), 0);
+        assert_eq!(concave.fuel(8), 22);
+        assert!(!concave.is_convex());
+        let costs: [&dyn FuelCost; 5] = [&Linear, &Triangular, &Quadratic, &piecewise, &concave];
+        for cost in costs {
+            let terms = cost.terms().unwrap();
+            for n in 0..20 {
+                assert_eq!(terms_fuel(&terms, n), cost.fuel(n));
+            }
+        }
+        // n·(n + 1) doesn't fit, half of it does
+        assert_eq!(Triangular.fuel(1 << 32), (1 << 63) + (1 << 31));
+    }
+
+    #[test]
+    #[should_panic(expected = "doesn't fit into a usize")]
+    fn test_overflow() {
+        // each crab alone fits
+        total(&[0, usize::MAX, usize::MAX], 0, &Linear);
+    }
+
+    #[test]
+    fn test_strategy() {
+        assert_eq!(
+            optimize_with(&EXAMPLE_DATA, &Linear).0,
+            Strategy::ClosedForm
+        );
+        assert_eq!(
+            optimize_with(&EXAMPLE_DATA, &*from_string("quadratic")).0,
+            Strategy::ClosedForm
+        );
+        assert_eq!(
+            optimize_with(&EXAMPLE_DATA, &*from_string("0:1,10:3")).0,
+            Strategy::Ternary
+        );
+        assert_eq!(
+            optimize_with(&EXAMPLE_DATA, &*from_string("0:3,10:1")).0,
+            Strategy::Scan
+        );
+        assert_eq!(optimize(&EXAMPLE_DATA, &Linear), (2, 37));
+        assert_eq!(optimize(&EXAMPLE_DATA, &Triangular), (5, 168));
+        assert_eq!(optimize(&EXAMPLE_DATA, &Searched(Triangular)), (5, 168));
+        // the mean is 4.9
+        assert_eq!(optimize(&EXAMPLE_DATA, &Quadratic).0, 5);
+    }
+
+    #[test]
+    fn test_optimize_matches_scan() {
+        let costs: Vec<Box<dyn FuelCost>> = vec![
+            Box::new(Linear),
+            Box::new(Triangular),
+            Box::new(Quadratic),
+            Box::new(Searched(Linear)),
+            Box::new(Searched(Triangular)),
+            Box::new(Searched(Quadratic)),
+            Box::new(Piecewise::from_string("0:1,5:2,20:7")),
+            Box::new(Piecewise::from_string("3:1")),
+            Box::new(Piecewise::from_string("0:4,5:1,8:2")),
+        ];
+        let mut next = random(5);
+        for round in 0..150 {
+            let len = 1 + next(20);
+            let range = [2, 40, 500][round % 3];
+            let positions = (0..len).map(|_| next(range)).collect::<Vec<_>>();
+            for cost in costs.iter() {
+                assert_eq!(
+                    optimize(&positions, cost.as_ref()),
+                    scan(&positions, super::range(&positions), cost.as_ref()),
+                    "{:?}",
+                    positions
+                );
+            }
+        }
+    }
+}
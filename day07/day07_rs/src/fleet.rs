@@ -0,0 +1,699 @@
+use crate::cost::{ternary, FuelCost, Terms, OVERFLOW};
+use std::collections::BTreeSet;
+
+/// A crab whose fuel use is multiplied by its weight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Crab {
+    pub position: usize,
+    pub weight: usize,
+}
+
+impl Crab {
+    pub fn new(position: usize) -> Self {
+        Self {
+            position,
+            weight: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FleetOptions {
+    /// At most this many positions the crabs align to.
+    pub targets: usize,
+    /// At most this many crabs per target, any number if `None`.
+    pub capacity: Option<usize>,
+}
+
+impl Default for FleetOptions {
+    fn default() -> Self {
+        Self {
+            targets: 1,
+            capacity: None,
+        }
+    }
+}
+
+/// Where the crabs go.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Plan {
+    /// The positions in use, from left to right.
+    pub targets: Vec<usize>,
+    /// The index into `targets` for every crab, in the order of the input.
+    pub assignment: Vec<usize>,
+    pub fuel: usize,
+    /// Whether no other plan takes less fuel. Otherwise the crabs are still sent to their
+    /// targets the best way, but other targets might be better.
+    pub optimal: bool,
+}
+
+impl Plan {
+    /// Number of crabs moving to every target.
+    pub fn crabs(&self) -> Vec<usize> {
+        let mut crabs = vec![0; self.targets.len()];
+        for &t in self.assignment.iter() {
+            crabs[t] += 1;
+        }
+        crabs
+    }
+}
+
+/// The fuel all crabs need to move to `to`, panics with `OVERFLOW` if it doesn't fit.
+pub fn total(crabs: &[Crab], to: usize, cost: &(impl FuelCost + ?Sized)) -> usize {
+    crabs
+        .iter()
+        .try_fold(0_usize, |sum, c| {
+            let fuel = cost.fuel(c.position.abs_diff(to));
+            sum.checked_add(c.weight.checked_mul(fuel)?)
+        })
+        .expect(OVERFLOW)
+}
+
+/// The fuel of runs of sorted crabs. For a cost made of `Terms`, sums of the weights and of the
+/// weighted positions and squares of the first crabs give the fuel of any run in logarithmic
+/// time, as long as those sums fit into an `i128`. Other costs add up the crabs of the run.
+struct Runs<'a, C: FuelCost + ?Sized> {
+    sorted: &'a [Crab],
+    cost: &'a C,
+    terms: Option<Terms>,
+    /// The sums for the first 0, 1, 2... crabs, if there are terms.
+    sums: Vec<[i128; 3]>,
+    /// The positions where the fuel of a run changes its slope, if it's linear in between.
+    kinks: Option<Vec<usize>>,
+}
+
+impl<'a, C: FuelCost + ?Sized> Runs<'a, C> {
+    fn new(sorted: &'a [Crab], cost: &'a C) -> Self {
+        let terms = cost.terms();
+        let mut sums = Vec::new();
+        if terms.is_some() {
+            let mut sum = [0; 3];
+            sums.push(sum);
+            for crab in sorted {
+                let (w, p) = (crab.weight as i128, crab.position as i128);
+                sum = [sum[0] + w, sum[1] + w * p, sum[2] + w * p * p];
+                sums.push(sum);
+            }
+        }
+        Self {
+            sorted,
+            cost,
+            kinks: kinks(sorted, terms.as_ref()),
+            terms,
+            sums,
+        }
+    }
+    /// The sums for the crabs from `start` up to `end`.
+    fn between(&self, start: usize, end: usize) -> [i128; 3] {
+        let (low, high) = (self.sums[start], self.sums[end]);
+        [high[0] - low[0], high[1] - low[1], high[2] - low[2]]
+    }
+    /// The fuel of the crabs from `start` up to `end` to `to`.
+    fn total(&self, start: usize, end: usize, to: usize) -> usize {
+        let Some(terms) = &self.terms else {
+            return total(&self.sorted[start..end], to, self.cost);
+        };
+        let run = &self.sorted[start..end];
+        let mut sum = 0;
+        for &(from, a, b) in terms.terms.iter() {
+            // the crabs up to `to - from` move m = to - from - p steps past `from`
+            let x = to as i128 - from as i128;
+            let split = start + run.partition_point(|c| c.position as i128 <= x);
+            let [w, p, pp] = self.between(start, split);
+            sum += a * (w * x * x - 2 * x * p + pp) + b * (w * x - p);
+            // and the ones from `to + from` on m = p - to - from steps
+            let x = (to + from) as i128;
+            let split = start + run.partition_point(|c| (c.position as i128) < x);
+            let [w, p, pp] = self.between(split, end);
+            sum += a * (pp - 2 * x * p + w * x * x) + b * (p - w * x);
+        }
+        usize::try_from(sum / terms.divisor).expect(OVERFLOW)
+    }
+    /// The best target for the crabs from `start` up to `end`, and its fuel. A convex cost is
+    /// searched from the first crab to the last, other costs try every kink in between, or
+    /// every position if the fuel isn't linear between the kinks.
+    fn best(&self, start: usize, end: usize) -> (usize, usize) {
+        let (low, high) = (self.sorted[start].position, self.sorted[end - 1].position);
+        let total = |to| self.total(start, end, to);
+        if self.cost.is_convex() {
+            return ternary(low..=high, total);
+        }
+        match &self.kinks {
+            Some(kinks) => {
+                let first = kinks.partition_point(|&k| k < low);
+                let last = kinks.partition_point(|&k| k <= high);
+                scan(kinks[first..last].iter().copied(), total)
+            }
+            None => scan(low..=high, total),
+        }
+    }
+}
+
+/// The first position with the least fuel.
+fn scan(candidates: impl Iterator<Item = usize>, total: impl Fn(usize) -> usize) -> (usize, usize) {
+    candidates
+        .map(|to| (to, total(to)))
+        .min_by_key(|&(_, fuel)| fuel)
+        .unwrap()
+}
+
+/// Every crab and every position a `from` of the terms away from one, sorted. Costs made of
+/// linear terms only change their slope there, so the least fuel of a run of crabs is at one
+/// of them. `None` for other costs.
+fn kinks(crabs: &[Crab], terms: Option<&Terms>) -> Option<Vec<usize>> {
+    let terms = terms.filter(|t| t.terms.iter().all(|&(_, a, _)| a == 0))?;
+    let mut kinks = Vec::with_capacity(crabs.len() * (2 * terms.terms.len() + 1));
+    for crab in crabs {
+        kinks.push(crab.position);
+        for &(from, _, _) in terms.terms.iter() {
+            kinks.extend(crab.position.checked_sub(from));
+            kinks.extend(crab.position.checked_add(from));
+        }
+    }
+    kinks.sort_unstable();
+    kinks.dedup();
+    Some(kinks)
+}
+
+/// The best target for some crabs, anywhere from the first one to the last.
+fn best_target(crabs: &[Crab], cost: &(impl FuelCost + ?Sized)) -> (usize, usize) {
+    let mut sorted = crabs.to_vec();
+    sorted.sort_by_key(|c| c.position);
+    Runs::new(&sorted, cost).best(0, sorted.len())
+}
+
+/// `least[k][i]` is the fuel for the first `i` crabs on at most `k` targets, with the start of
+/// the last run, or `None` if they got by with fewer targets.
+type Least = Vec<Vec<Option<(usize, Option<usize>)>>>;
+
+/// The next row of `Least` from the one for a target less, with runs of at most `capacity`
+/// crabs. For `a <= b <= c <= d`, the runs from `a` to `c` and from `b` to `d` never take more
+/// fuel than the runs from `a` to `d` and from `b` to `c`, as every crab may move to the nearer
+/// of their targets. So
+/// the best start of the last run never moves left as the run ends further right, and the
+/// runs ending in the middle are tried first to narrow down the starts for the others.
+fn next_least<C: FuelCost + ?Sized>(
+    runs: &Runs<C>,
+    previous: &[Option<(usize, Option<usize>)>],
+    capacity: usize,
+) -> Vec<Option<(usize, Option<usize>)>> {
+    let n = previous.len() - 1;
+    let mut least = vec![None; n + 1];
+    // the ends to do, with the starts to try for them
+    let mut todo = vec![(0, n, 0, n)];
+    while let Some((low, high, first, last)) = todo.pop() {
+        let end = low + (high - low) / 2;
+        let mut best: Option<(usize, usize)> = None;
+        // an empty run at the end stands for fewer targets, and wins a tie like later starts
+        let starts = first.max(end.saturating_sub(capacity))..=last.min(end);
+        for (start, fuel) in starts.filter_map(|start| Some((start, previous[start]?.0))) {
+            let fuel = if start < end {
+                fuel.checked_add(runs.best(start, end).1).expect(OVERFLOW)
+            } else {
+                fuel
+            };
+            if best.is_none_or(|(least, _)| fuel <= least) {
+                best = Some((fuel, start));
+            }
+        }
+        least[end] = best.map(|(fuel, start)| (fuel, (start < end).then_some(start)));
+        let split = best.map_or(last.min(end), |(_, start)| start);
+        if low < end {
+            todo.push((low, end - 1, first, split));
+        }
+        if end < high {
+            todo.push((end + 1, high, split, last));
+        }
+    }
+    least
+}
+
+/// Aligns the crabs to at most `options.targets` positions, each taking at most
+/// `options.capacity` crabs, with the least fuel.
+///
+/// The crabs are sorted by position and every target takes a run of neighbouring crabs, found
+/// by dynamic programming over the runs. That's the best plan when there's no capacity, or
+/// when all crabs weigh the same and the cost is convex. Otherwise a heavy crab may be better
+/// off at a nearer target than a light one next to it, so the crabs are assigned to the targets
+/// the best way and the targets are moved to the best position for their crabs, for as long as
+/// that saves fuel. Such a plan isn't marked `optimal`.
+pub fn plan(crabs: &[Crab], options: FleetOptions, cost: &(impl FuelCost + ?Sized)) -> Plan {
+    let n = crabs.len();
+    let capacity = options.capacity.unwrap_or(n).min(n);
+    assert!(
+        options.targets.saturating_mul(capacity) >= n,
+        "{} targets for at most {} crabs each can't take {} crabs.",
+        options.targets,
+        capacity,
+        n
+    );
+    if n == 0 {
+        return Plan {
+            targets: Vec::new(),
+            assignment: Vec::new(),
+            fuel: 0,
+            optimal: true,
+        };
+    }
+    let mut order = (0..n).collect::<Vec<_>>();
+    order.sort_by_key(|&i| crabs[i].position);
+    let sorted = order.iter().map(|&i| crabs[i]).collect::<Vec<_>>();
+    let runs = Runs::new(&sorted, cost);
+    let mut least: Least = vec![vec![None; n + 1]];
+    least[0][0] = Some((0, None));
+    for k in 1..=options.targets {
+        least.push(next_least(&runs, &least[k - 1], capacity));
+    }
+
+    let mut targets = Vec::new();
+    let mut assignment = vec![0; n];
+    let (mut k, mut end) = (options.targets, n);
+    while end > 0 {
+        if let (_, Some(start)) = least[k][end].unwrap() {
+            for &i in order[start..end].iter() {
+                assignment[i] = targets.len();
+            }
+            targets.push(runs.best(start, end).0);
+            end = start;
+        }
+        k -= 1;
+    }
+    let same_weight = crabs.iter().all(|c| c.weight == crabs[0].weight);
+    let optimal = capacity == n || same_weight && cost.is_convex();
+    if !optimal {
+        improve(crabs, &mut targets, &mut assignment, capacity, cost);
+    }
+
+    // number the targets from left to right
+    let mut by_position = (0..targets.len()).collect::<Vec<_>>();
+    by_position.sort_by_key(|&t| (targets[t], t));
+    let mut renumbered = vec![0; targets.len()];
+    for (new, &old) in by_position.iter().enumerate() {
+        renumbered[old] = new;
+    }
+    let assignment = assignment
+        .iter()
+        .map(|&t| renumbered[t])
+        .collect::<Vec<_>>();
+    let targets = by_position.iter().map(|&t| targets[t]).collect::<Vec<_>>();
+    let fuel = fuel_of(crabs, &targets, &assignment, cost);
+    Plan {
+        targets,
+        assignment,
+        fuel,
+        optimal,
+    }
+}
+
+/// The fuel of all crabs moving to their targets.
+fn fuel_of(
+    crabs: &[Crab],
+    targets: &[usize],
+    assignment: &[usize],
+    cost: &(impl FuelCost + ?Sized),
+) -> usize {
+    crabs
+        .iter()
+        .zip(assignment.iter())
+        .try_fold(0_usize, |sum, (c, &t)| {
+            sum.checked_add(total(&[*c], targets[t], cost))
+        })
+        .expect(OVERFLOW)
+}
+
+/// Assigns the crabs to the targets the best way and moves the targets to the best position
+/// for their crabs, for as long as that saves fuel.
+fn improve(
+    crabs: &[Crab],
+    targets: &mut [usize],
+    assignment: &mut Vec<usize>,
+    capacity: usize,
+    cost: &(impl FuelCost + ?Sized),
+) {
+    let mut fuel = fuel_of(crabs, targets, assignment, cost);
+    loop {
+        *assignment = assign(crabs, targets, capacity, cost);
+        for (t, target) in targets.iter_mut().enumerate() {
+            let group = (0..crabs.len())
+                .filter(|&i| assignment[i] == t)
+                .map(|i| crabs[i])
+                .collect::<Vec<_>>();
+            if !group.is_empty() {
+                *target = best_target(&group, cost).0;
+            }
+        }
+        let next = fuel_of(crabs, targets, assignment, cost);
+        if next >= fuel {
+            break;
+        }
+        fuel = next;
+    }
+}
+
+/// The assignment of the crabs to the targets, at most `capacity` crabs each, with the least
+/// fuel. As in the Hungarian method, the crabs join one after the other, each at the end of the
+/// cheapest chain of crabs moving on to make room, which keeps the assignment of the crabs so
+/// far the best one.
+fn assign(
+    crabs: &[Crab],
+    targets: &[usize],
+    capacity: usize,
+    cost: &(impl FuelCost + ?Sized),
+) -> Vec<usize> {
+    let k = targets.len();
+    let fuel = |j: usize, t: usize| total(&[crabs[j]], targets[t], cost) as i128;
+    // moves[s][t] has the crabs at target s by how much the fuel changes when they move to t
+    let mut moves = vec![vec![BTreeSet::new(); k]; k];
+    let update = |moves: &mut [Vec<BTreeSet<(i128, usize)>>], j, s, insert| {
+        for t in (0..k).filter(|&t| t != s) {
+            let change = (fuel(j, t) - fuel(j, s), j);
+            if insert {
+                moves[s][t].insert(change);
+            } else {
+                moves[s][t].remove(&change);
+            }
+        }
+    };
+    let mut assignment = vec![0; crabs.len()];
+    let mut crabs_at = vec![0; k];
+    for i in 0..crabs.len() {
+        // the cheapest chain to every target, there's no cycle that saves fuel
+        let mut least = (0..k).map(|t| fuel(i, t)).collect::<Vec<_>>();
+        let mut via = vec![None; k];
+        for _ in 1..k {
+            let mut changed = false;
+            for s in 0..k {
+                for t in 0..k {
+                    if let Some(&(change, j)) = moves[s][t].first() {
+                        if least[s] + change < least[t] {
+                            least[t] = least[s] + change;
+                            via[t] = Some((s, j));
+                            changed = true;
+                        }
+                    }
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+        let mut t = (0..k)
+            .filter(|&t| crabs_at[t] < capacity)
+            .min_by_key(|&t| least[t])
+            .unwrap();
+        crabs_at[t] += 1;
+        while let Some((s, j)) = via[t] {
+            update(&mut moves, j, s, false);
+            update(&mut moves, j, t, true);
+            assignment[j] = t;
+            t = s;
+        }
+        update(&mut moves, i, t, true);
+        assignment[i] = t;
+    }
+    assignment
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cost::{optimize, Linear, Piecewise, Quadratic, Triangular};
+    use crate::fixtures::{random, EXAMPLE_DATA};
+
+    fn crabs(positions: &[usize]) -> Vec<Crab> {
+        positions.iter().map(|&p| Crab::new(p)).collect()
+    }
+
+    /// The least fuel of any way to send the crabs to `targets` targets, at most `capacity`
+    /// to each, given the fuel of the crabs of a target.
+    fn brute_force(
+        crabs: &[Crab],
+        targets: usize,
+        capacity: usize,
+        group_fuel: impl Fn(usize, &[Crab]) -> usize,
+    ) -> usize {
+        let mut best = usize::MAX;
+        let mut labels = vec![0; crabs.len()];
+        loop {
+            let mut fuel = 0;
+            for t in 0..targets {
+                let group = (0..crabs.len())
+                    .filter(|&i| labels[i] == t)
+                    .map(|i| crabs[i])
+                    .collect::<Vec<_>>();
+                if group.len() > capacity {
+                    fuel = usize::MAX;
+                    break;
+                }
+                if !group.is_empty() {
+                    fuel += group_fuel(t, &group);
+                }
+            }
+            best = best.min(fuel);
+            // count through all labellings
+            let Some(i) = labels.iter().position(|&l| l + 1 < targets) else {
+                return best;
+            };
+            labels[i] += 1;
+            labels[..i].fill(0);
+        }
+    }
+
+    #[test]
+    fn test_single_target() {
+        let example = crabs(&EXAMPLE_DATA);
+        let plan = plan(&example, FleetOptions::default(), &Linear);
+        assert_eq!(plan.targets, vec![2]);
+        assert_eq!(plan.assignment, vec![0; 10]);
+        assert_eq!(plan.fuel, 37);
+        assert_eq!(
+            super::plan(&example, FleetOptions::default(), &Triangular).fuel,
+            168
+        );
+        // a crab weighing three moves like three crabs
+        let mut weighted = crabs(&[0, 3, 10]);
+        weighted[2].weight = 3;
+        let plan = super::plan(&weighted, FleetOptions::default(), &Triangular);
+        assert_eq!(
+            (plan.targets[0], plan.fuel),
+            optimize(&[0, 3, 10, 10, 10], &Triangular)
+        );
+    }
+
+    #[test]
+    fn test_several_targets() {
+        let options = FleetOptions {
+            targets: 2,
+            capacity: None,
+        };
+        let plan = plan(&crabs(&[9, 1, 10, 0, 2, 11]), options, &Linear);
+        assert_eq!(plan.targets, vec![1, 10]);
+        assert_eq!(plan.assignment, vec![1, 0, 1, 0, 0, 1]);
+        assert_eq!(plan.fuel, 4);
+        assert_eq!(plan.crabs(), vec![3, 3]);
+
+        // at most two crabs per target, so one of the left crabs joins the right ones
+        let options = FleetOptions {
+            targets: 3,
+            capacity: Some(2),
+        };
+        let plan = super::plan(&crabs(&[0, 0, 0, 10, 20, 20]), options, &Linear);
+        assert_eq!(plan.targets, vec![0, 0, 20]);
+        assert_eq!(plan.crabs(), vec![2, 2, 2]);
+        assert_eq!(plan.fuel, 10);
+
+        // more targets than crabs
+        let options = FleetOptions {
+            targets: 5,
+            capacity: None,
+        };
+        let plan = super::plan(&crabs(&[3, 8]), options, &Quadratic);
+        assert_eq!(plan.targets, vec![3, 8]);
+        assert_eq!(plan.fuel, 0);
+    }
+
+    #[test]
+    fn test_assign() {
+        // the light crab makes room for the heavy one
+        let mut fleet = crabs(&[5, 6]);
+        fleet[1].weight = 5;
+        assert_eq!(assign(&fleet, &[0, 6], 1, &Linear), vec![0, 1]);
+        let mut fleet = crabs(&[4, 5, 6]);
+        fleet[2].weight = 5;
+        assert_eq!(assign(&fleet, &[0, 6], 2, &Linear), vec![0, 1, 1]);
+        assert_eq!(assign(&fleet, &[6, 0], 2, &Linear), vec![1, 0, 0]);
+        // a chain of crabs moving on to the next target
+        let fleet = crabs(&[0, 10, 20, 1]);
+        assert_eq!(assign(&fleet, &[0, 10, 20], 2, &Linear), vec![0, 1, 2, 0]);
+        assert_eq!(
+            assign(&fleet, &[0, 10, 20, 30], 1, &Linear),
+            vec![0, 1, 2, 3]
+        );
+    }
+
+    #[test]
+    fn test_far_apart() {
+        // the run costs take no memory for the positions between the crabs
+        let mut fleet = crabs(&[0, 100_000_000, 3, 99_999_998]);
+        fleet[0].weight = 4;
+        let options = FleetOptions {
+            targets: 2,
+            capacity: Some(2),
+        };
+        let plan = plan(&fleet, options, &Triangular);
+        assert_eq!(plan.targets, vec![0, 99_999_999]);
+        assert_eq!(plan.fuel, 6 + 1 + 1);
+        assert!(!plan.optimal);
+        let plan = super::plan(&fleet, FleetOptions::default(), &Quadratic);
+        let positions = [0, 0, 0, 0, 100_000_000, 3, 99_999_998];
+        assert_eq!(
+            (plan.targets[0], plan.fuel),
+            optimize(&positions, &Quadratic)
+        );
+
+        // a cost that isn't convex only tries the positions where the fuel changes its slope
+        let cost = Piecewise::from_string("0:3,10:1");
+        let plan = super::plan(&crabs(&[0, 300_000_000]), FleetOptions::default(), &cost);
+        assert_eq!((plan.targets, plan.fuel), (vec![0], 300_000_020));
+        let mut fleet = crabs(&[0, 300_000_000, 300_000_003]);
+        fleet[1].weight = 2;
+        let plan = super::plan(&fleet, options, &cost);
+        assert_eq!((plan.targets, plan.fuel), (vec![0, 300_000_000], 9));
+        assert!(!plan.optimal);
+    }
+
+    #[test]
+    fn test_zero_weights() {
+        // a crab weighing nothing moves for free, but still takes room at its target
+        let mut fleet = crabs(&[0, 10, 1000]);
+        fleet[2].weight = 0;
+        let plan = plan(&fleet, FleetOptions::default(), &Linear);
+        assert_eq!((plan.targets, plan.fuel), (vec![0], 10));
+        let options = FleetOptions {
+            targets: 2,
+            capacity: Some(2),
+        };
+        let cost = Piecewise::from_string("0:3,10:1");
+        let plan = super::plan(&fleet, options, &cost);
+        assert_eq!(plan.crabs(), vec![1, 2]);
+        assert_eq!((plan.targets, plan.fuel), (vec![0, 10], 0));
+        assert!(!plan.optimal);
+        fleet[0].weight = 0;
+        fleet[1].weight = 0;
+        let plan = super::plan(&fleet, FleetOptions::default(), &cost);
+        assert_eq!((plan.targets, plan.fuel), (vec![0], 0));
+    }
+
+    #[test]
+    fn test_capacity_for_all() {
+        // room for every crab at every target is as good as no capacity, whatever they weigh
+        let mut fleet = crabs(&[9, 0, 1, 10, 2]);
+        fleet[1].weight = 5;
+        fleet[3].weight = 0;
+        for cost in [
+            &Triangular as &dyn FuelCost,
+            &Piecewise::from_string("0:3,2:1"),
+        ] {
+            let options = FleetOptions {
+                targets: 2,
+                capacity: Some(5),
+            };
+            let plan = plan(&fleet, options, cost);
+            assert!(plan.optimal);
+            let free = FleetOptions {
+                capacity: None,
+                ..options
+            };
+            assert_eq!(plan, super::plan(&fleet, free, cost));
+        }
+    }
+
+    #[test]
+    fn test_more_targets_than_positions() {
+        let fleet = crabs(&[3, 8, 3, 8, 8]);
+        for cost in [
+            &Linear as &dyn FuelCost,
+            &Piecewise::from_string("0:3,10:1"),
+        ] {
+            let options = FleetOptions {
+                targets: 4,
+                capacity: None,
+            };
+            let plan = plan(&fleet, options, cost);
+            assert_eq!((plan.targets, plan.fuel), (vec![3, 8], 0));
+            assert_eq!(plan.assignment, vec![0, 1, 0, 1, 1]);
+            // the third crab at 8 needs a target of its own
+            let options = FleetOptions {
+                capacity: Some(2),
+                ..options
+            };
+            let plan = super::plan(&fleet, options, cost);
+            assert_eq!(plan.optimal, cost.is_convex());
+            assert!(plan.crabs().iter().all(|&n| n <= 2));
+            assert_eq!((plan.targets, plan.fuel), (vec![3, 8, 8], 0));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "can't take 5 crabs")]
+    fn test_too_little_capacity() {
+        let options = FleetOptions {
+            targets: 2,
+            capacity: Some(2),
+        };
+        plan(&crabs(&[1, 2, 3, 4, 5]), options, &Linear);
+    }
+
+    #[test]
+    fn test_plan_matches_brute_force() {
+        let costs: Vec<Box<dyn FuelCost>> = vec![
+            Box::new(Linear),
+            Box::new(Triangular),
+            Box::new(Piecewise::from_string("0:1,3:4")),
+            Box::new(Piecewise::from_string("0:3,2:1")),
+        ];
+        let mut next = random(3);
+        for round in 0..60 {
+            // weighted crabs without capacity, crabs of the same weight with capacity, or both
+            let weighted = round % 3 != 1;
+            let len = 1 + next(6);
+            let crabs = (0..len)
+                .map(|_| Crab {
+                    position: next(21),
+                    weight: if weighted { next(4) } else { 1 },
+                })
+                .collect::<Vec<_>>();
+            let targets = 1 + next(3);
+            let options = FleetOptions {
+                targets,
+                capacity: (round % 3 != 0).then(|| len.div_ceil(targets) + next(2)),
+            };
+            let capacity = options.capacity.unwrap_or(len);
+            let same_weight = crabs.iter().all(|c| c.weight == crabs[0].weight);
+            for cost in costs.iter() {
+                let cost = cost.as_ref();
+                let plan = plan(&crabs, options, cost);
+                let best = brute_force(&crabs, targets, capacity, |_, group| {
+                    (0..=20).map(|to| total(group, to, cost)).min().unwrap()
+                });
+                assert_eq!(
+                    plan.optimal,
+                    capacity >= len || same_weight && cost.is_convex()
+                );
+                if plan.optimal {
+                    assert_eq!(plan.fuel, best, "{:?}", crabs);
+                } else {
+                    assert!(plan.fuel >= best, "{:?}", crabs);
+                }
+                // the fuel adds up for the assignment, which is the best one for the targets
+                let fuel = fuel_of(&crabs, &plan.targets, &plan.assignment, cost);
+                assert_eq!(fuel, plan.fuel);
+                let assigned = brute_force(&crabs, plan.targets.len(), capacity, |t, group| {
+                    total(group, plan.targets[t], cost)
+                });
+                assert_eq!(fuel, assigned, "{:?}", crabs);
+                assert!(plan.crabs().iter().all(|&n| n <= capacity));
+            }
+        }
+    }
+}
@@ -1,80 +1,131 @@
+use cost::{FuelCost, Linear, Triangular};
+use fleet::{Crab, FleetOptions};
 use std::fs;
 
+mod cost;
+mod fleet;
+
 fn main() {
     let input = fs::read_to_string("input.txt")
         .unwrap()
         .split(',')
         .map(|n| n.parse::<usize>().unwrap())
         .collect::<Vec<_>>();
+    let mut check = false;
+    let mut costs = Vec::new();
+    let mut weights = None;
+    let mut options = FleetOptions::default();
+    let mut fleet = false;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().expect("missing argument value");
+        match arg.as_str() {
+            // compare with trying every position
+            "--check" => check = true,
+            // linear, triangular, quadratic or piecewise rates like 0:1,10:3, may be repeated
+            "--cost" => costs.push(value()),
+            // a file with a weight for every crab, and several targets with a capacity
+            "--weights" => {
+                weights = Some(fs::read_to_string(value()).unwrap());
+                fleet = true;
+            }
+            "--targets" => {
+                options.targets = value().parse().unwrap();
+                fleet = true;
+            }
+            "--capacity" => {
+                options.capacity = Some(value().parse().unwrap());
+                fleet = true;
+            }
+            _ => panic!("Unknown argument {}", arg),
+        }
+    }
 
     let part1 = solve1(&input);
     println!("Part 1: {:?}", &part1);
     let part2 = solve2(&input);
     println!("Part 2: {:?}", &part2);
-}
-pub fn solve1(positions: &[usize]) -> (usize, usize) {
-    let max = positions.iter().max().unwrap();
-    let mut move_to = 0;
-    let mut fuel_used = 0;
-    let mut temp = 0;
-    for n in 1..*max {
-        for p in positions.iter() {
-            match p.cmp(&n) {
-                std::cmp::Ordering::Less => temp += n - *p,
-                std::cmp::Ordering::Equal => continue,
-                std::cmp::Ordering::Greater => temp += *p - n,
+    if check {
+        let scans = (solve1_scan(&input), solve2_scan(&input));
+        if scans == (part1, part2) {
+            println!("Checked against the exhaustive scan.");
+        } else {
+            eprintln!("The exhaustive scan gives {:?} and {:?}.", scans.0, scans.1);
+            std::process::exit(1);
+        }
+    }
+    if fleet {
+        let mut crabs = input.iter().map(|&p| Crab::new(p)).collect::<Vec<_>>();
+        if let Some(weights) = weights {
+            let weights = weights.trim().split(',').collect::<Vec<_>>();
+            assert_eq!(weights.len(), crabs.len(), "need a weight for every crab");
+            for (crab, weight) in crabs.iter_mut().zip(weights) {
+                crab.weight = weight.trim().parse().unwrap();
             }
         }
-        if fuel_used == 0 {
-            fuel_used = temp;
-            temp = 0;
-            continue;
+        if costs.is_empty() {
+            costs.push("linear".to_string());
         }
-        if temp < fuel_used {
-            fuel_used = temp;
-            move_to = n;
-            temp = 0
+        for name in costs {
+            let plan = fleet::plan(&crabs, options, cost::from_string(&name).as_ref());
+            let proven = if plan.optimal {
+                "the least possible"
+            } else {
+                "maybe not the least"
+            };
+            println!("{:>12}: {} fuel, {}", name, plan.fuel, proven);
+            for (target, crabs) in plan.targets.iter().zip(plan.crabs()) {
+                println!("{:>12}  {} crabs to {}", "", crabs, target);
+            }
         }
+        return;
     }
-    (move_to, fuel_used)
+    for name in costs {
+        let cost = cost::from_string(&name);
+        let (strategy, best) = cost::optimize_with(&input, cost.as_ref());
+        println!("{:>12}: {:?} ({:?})", name, best, strategy);
+    }
+}
+/// The position all crabs move to with the least fuel, where each step costs one, and that
+/// fuel. On a tie the lowest position is taken.
+pub fn solve1(positions: &[usize]) -> (usize, usize) {
+    cost::optimize(positions, &Linear)
 }
 
+/// Like `solve1`, but the n-th step costs n fuel.
 pub fn solve2(positions: &[usize]) -> (usize, usize) {
-    let max = positions.iter().max().unwrap();
-    let mut move_to = 0;
-    let mut fuel_used = 0;
-    let mut temp: usize = 0;
-    for n in 1..*max {
-        for p in positions {
-            temp += calculate_fuel_use(*p, n);
-        }
-        if fuel_used == 0 {
-            fuel_used = temp;
-            move_to = n;
-            temp = 0;
-            continue;
-        }
-        if temp < fuel_used {
-            fuel_used = temp;
-            move_to = n;
-            temp = 0;
-        }
-    }
-    (move_to, fuel_used)
+    cost::optimize(positions, &Triangular)
+}
+
+/// `solve1` trying every position from the first crab to the last, as a reference.
+pub fn solve1_scan(positions: &[usize]) -> (usize, usize) {
+    cost::scan(positions, cost::range(positions), &Linear)
+}
+
+/// `solve2` trying every position from the first crab to the last, as a reference.
+pub fn solve2_scan(positions: &[usize]) -> (usize, usize) {
+    cost::scan(positions, cost::range(positions), &Triangular)
 }
 
 pub fn calculate_fuel_use(from: usize, to: usize) -> usize {
-    let n = match from.cmp(&to) {
-        std::cmp::Ordering::Less => to - from,
-        std::cmp::Ordering::Equal => 0,
-        std::cmp::Ordering::Greater => from - to,
-    };
-    n * (n + 1) / 2
+    Triangular.fuel(from.abs_diff(to))
+}
+#[cfg(test)]
+mod fixtures {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    pub static EXAMPLE_DATA: [usize; 10] = [16, 1, 2, 0, 4, 2, 7, 1, 2, 14];
+
+    /// Seeded random numbers below `range`.
+    pub fn random(seed: u64) -> impl FnMut(usize) -> usize {
+        let mut rng = StdRng::seed_from_u64(seed);
+        move |range| rng.gen_range(0..range)
+    }
 }
 #[cfg(test)]
 mod tests {
     use super::*;
-    static EXAMPLE_DATA: [usize; 10] = [16, 1, 2, 0, 4, 2, 7, 1, 2, 14];
+    use crate::fixtures::{random, EXAMPLE_DATA};
     #[test]
     fn test_solve1() {
         assert_eq!(solve1(&EXAMPLE_DATA), (2_usize, 37_usize));
@@ -90,4 +141,41 @@ mod tests {
     fn test_solve2() {
         assert_eq!(solve2(&EXAMPLE_DATA), (5_usize, 168_usize));
     }
+
+    #[test]
+    fn test_scan() {
+        assert_eq!(solve1_scan(&EXAMPLE_DATA), (2, 37));
+        assert_eq!(solve2_scan(&EXAMPLE_DATA), (5, 168));
+        // the first and the last position count as well
+        assert_eq!(solve1_scan(&[0, 0, 0, 9]), (0, 9));
+        assert_eq!(solve2_scan(&[3, 9, 9, 9]), (8, 15 + 3));
+        assert_eq!(solve1_scan(&[4]), (4, 0));
+    }
+
+    #[test]
+    fn test_fast_matches_scan() {
+        assert_eq!(solve1(&[0, 0, 0, 9]), (0, 9));
+        assert_eq!(solve1(&[5, 1]), (1, 4));
+        assert_eq!(solve2(&[4]), (4, 0));
+        assert_eq!(solve2(&[0, 1]), (0, 1));
+        // random crabs, some close together and some far apart
+        let mut next = random(11);
+        for round in 0..200 {
+            let len = 1 + next(30);
+            let range = [3, 50, 2000][round % 3];
+            let positions = (0..len).map(|_| next(range)).collect::<Vec<_>>();
+            assert_eq!(
+                solve1(&positions),
+                solve1_scan(&positions),
+                "{:?}",
+                positions
+            );
+            assert_eq!(
+                solve2(&positions),
+                solve2_scan(&positions),
+                "{:?}",
+                positions
+            );
+        }
+    }
 }